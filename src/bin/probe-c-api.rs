@@ -0,0 +1,377 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Command-line interface for one-off probes.
+//!
+//! The exit status is 0 on success (or a "yes" answer), 1 for a "no" answer
+//! or a spec with items that could not be bound, and 2 if the probe or the
+//! command line itself failed.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use probe_c_api::{ApiSpec, CProbeError, EvaluationStrategy, ProbeBuilder};
+use probe_c_api::CTypeKind::*;
+
+const USAGE: &str = "\
+Usage: probe-c-api [OPTIONS] COMMAND [--] ARGUMENT
+
+Commands:
+    size-of TYPE          Print the size of a type, in bytes
+    align-of TYPE         Print the alignment of a type, in bytes
+    constant EXPRESSION   Print the value of an integer or floating-point
+                          constant
+    is-defined MACRO      Check whether a macro is defined
+    has-function NAME     Check whether a function is declared and links
+    run-spec FILE         Print bindings for the items in an API spec file
+
+Options:
+    -H, --header HEADER   Include a header, e.g. `sys/stat.h` (angle brackets
+                          are added unless the name is already delimited)
+    -I DIR                Add a directory to search for headers
+    -D NAME[=VALUE]       Define a macro
+    -l LIBRARY            Link against a library
+    -L DIR                Add a directory to search for libraries
+    --std STD             Set the language standard, e.g. `c11`
+    --flag FLAG           Pass another flag to the compiler
+    --cc PROGRAM          Use a different compiler command
+    --clang               Use Clang instead of GCC
+    --compile-only        Never run probe programs (see `CompileOnly`)
+    --json                Print results as JSON
+    -h, --help            Print this message
+
+Arguments after `--` are never taken as options, e.g. `constant -- -1`.
+";
+
+// Exit statuses.
+const EXIT_YES: i32 = 0;
+const EXIT_NO: i32 = 1;
+const EXIT_FAILURE: i32 = 2;
+
+// Settings gathered from the command line.
+#[derive(Debug, Default)]
+struct Options {
+    headers: Vec<String>,
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<String>,
+    libraries: Vec<String>,
+    library_dirs: Vec<PathBuf>,
+    std: Option<String>,
+    flags: Vec<String>,
+    program: Option<PathBuf>,
+    clang: bool,
+    compile_only: bool,
+    json: bool,
+    command: Option<String>,
+    argument: Option<String>,
+}
+
+impl Options {
+    // Add the command-line settings to `builder`.
+    fn apply(&self, mut builder: ProbeBuilder) -> ProbeBuilder {
+        for header in &self.headers {
+            builder = if header.starts_with('<') || header.starts_with('"') {
+                builder.header(header)
+            } else {
+                builder.header(&format!("<{}>", header))
+            };
+        }
+        for dir in &self.include_dirs {
+            builder = builder.include_dir(dir);
+        }
+        for define in &self.defines {
+            let mut parts = define.splitn(2, '=');
+            let name = parts.next().unwrap();
+            builder = match parts.next() {
+                Some(value) => builder.define_value(name, value),
+                None => builder.define(name),
+            };
+        }
+        for library in &self.libraries {
+            builder = builder.library(library);
+        }
+        for dir in &self.library_dirs {
+            builder = builder.library_dir(dir);
+        }
+        if let Some(ref std) = self.std {
+            builder = builder.std(std);
+        }
+        for flag in &self.flags {
+            builder = builder.flag(flag);
+        }
+        if self.clang {
+            builder = builder.clang();
+        }
+        if let Some(ref program) = self.program {
+            builder = builder.compiler_program(program);
+        }
+        if self.compile_only {
+            builder = builder.evaluation_strategy(
+                EvaluationStrategy::CompileOnly);
+        }
+        builder
+    }
+}
+
+fn parse_args<I>(mut args: I) -> Result<Options, String>
+    where I: Iterator<Item=String> {
+    let mut options = Options::default();
+    let mut options_ended = false;
+    while let Some(arg) = args.next() {
+        if options_ended || !arg.starts_with('-') || arg == "-" {
+            if options.command.is_none() {
+                options.command = Some(arg);
+            } else if options.argument.is_none() {
+                options.argument = Some(arg);
+            } else {
+                return Err(format!("unexpected argument `{}`", arg));
+            }
+            continue;
+        }
+        if arg == "--" {
+            options_ended = true;
+            continue;
+        }
+        // Single-letter options may have their value attached, e.g. `-Ifoo`.
+        let (option, attached) = if arg.len() > 2 && arg.starts_with('-')
+                                    && !arg.starts_with("--")
+                                    && arg.is_char_boundary(2) {
+            (arg[..2].to_string(), Some(arg[2..].to_string()))
+        } else {
+            (arg.clone(), None)
+        };
+        let takes_value = matches!(&option[..],
+                                   "-H" | "--header" | "-I" | "-D" | "-l" |
+                                   "-L" | "--std" | "--flag" | "--cc");
+        if !takes_value {
+            match &arg[..] {
+                "--clang" => { options.clang = true; }
+                "--compile-only" => { options.compile_only = true; }
+                "--json" => { options.json = true; }
+                "-h" | "--help" => {
+                    return Err(String::new());
+                }
+                _ => {
+                    return Err(format!("unknown option `{}` (put `--` \
+                                        before arguments that start with \
+                                        `-`)", arg));
+                }
+            }
+            continue;
+        }
+        let value = match attached.or_else(|| args.next()) {
+            Some(value) => value,
+            None => {
+                return Err(format!("`{}` requires a value", option));
+            }
+        };
+        match &option[..] {
+            "-H" | "--header" => { options.headers.push(value); }
+            "-I" => { options.include_dirs.push(PathBuf::from(value)); }
+            "-D" => { options.defines.push(value); }
+            "-l" => { options.libraries.push(value); }
+            "-L" => { options.library_dirs.push(PathBuf::from(value)); }
+            "--std" => { options.std = Some(value); }
+            "--flag" => { options.flags.push(value); }
+            _ => { options.program = Some(PathBuf::from(value)); }
+        }
+    }
+    if options.command.is_none() || options.argument.is_none() {
+        return Err("expected a command and an argument".to_string());
+    }
+    Ok(options)
+}
+
+// The result of a command, in a form that can be printed either for people
+// or as JSON.
+enum Answer {
+    Number(String),
+    Flag(bool),
+    Function { declared: bool, links: bool },
+    Bindings { source: String, diagnostics: Vec<String> },
+}
+
+impl Answer {
+    fn exit_status(&self) -> i32 {
+        match *self {
+            Answer::Number(..) => EXIT_YES,
+            Answer::Flag(flag) => if flag { EXIT_YES } else { EXIT_NO },
+            Answer::Function { declared, links } => {
+                if declared && links { EXIT_YES } else { EXIT_NO }
+            }
+            Answer::Bindings { ref diagnostics, .. } => {
+                if diagnostics.is_empty() { EXIT_YES } else { EXIT_NO }
+            }
+        }
+    }
+
+    fn human(&self) -> String {
+        let yes_no = |flag| if flag { "yes" } else { "no" };
+        match *self {
+            Answer::Number(ref number) => number.clone(),
+            Answer::Flag(flag) => yes_no(flag).to_string(),
+            Answer::Function { declared, links } => {
+                format!("declared: {}\nlinks: {}", yes_no(declared),
+                        yes_no(links))
+            }
+            Answer::Bindings { ref source, .. } => {
+                source.trim_end().to_string()
+            }
+        }
+    }
+
+    // The members of a JSON object that describe the answer.
+    fn json_members(&self) -> String {
+        match *self {
+            Answer::Number(ref number) => {
+                // JSON has no infinities or NaNs.
+                if number.parse::<f64>().map_or(false, f64::is_finite) {
+                    format!("\"value\": {}", number)
+                } else {
+                    format!("\"value\": {}", json_string(number))
+                }
+            }
+            Answer::Flag(flag) => format!("\"value\": {}", flag),
+            Answer::Function { declared, links } => {
+                format!("\"value\": {{\"declared\": {}, \"links\": {}}}",
+                        declared, links)
+            }
+            Answer::Bindings { ref source, ref diagnostics } => {
+                let diagnostics: Vec<String> =
+                    diagnostics.iter().map(|d| json_string(d)).collect();
+                format!("\"source\": {}, \"diagnostics\": [{}]",
+                        json_string(source), diagnostics.join(", "))
+            }
+        }
+    }
+}
+
+// Write a string as a JSON string literal.
+fn json_string(string: &str) -> String {
+    let mut json = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(json, "\\u{:04x}", c as u32).unwrap();
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// A failed command, with a message for the user.
+fn failure<T, E: ToString>(error: E) -> Result<T, String> {
+    Err(error.to_string())
+}
+
+fn constant_value(probe: &probe_c_api::Probe, expression: &str)
+                  -> Result<String, CProbeError> {
+    let type_ = format!("__typeof__({})", expression);
+    Ok(match probe.c_type(&type_)?.kind {
+        CFloat => format!("{:?}", probe.double_constant(expression)?.value),
+        CInteger{ signed: true } => {
+            probe.wide_signed_integer_constant(expression)?.to_string()
+        }
+        _ => probe.wide_unsigned_integer_constant(expression)?.to_string(),
+    })
+}
+
+fn run_command(options: &Options, command: &str, argument: &str)
+               -> Result<Answer, String> {
+    if command == "run-spec" {
+        let spec = ApiSpec::from_file(Path::new(argument))
+                           .or_else(failure)?;
+        let builder = options.apply(spec.probe_builder().or_else(failure)?);
+        let probe = builder.build().or_else(failure)?;
+        let bindings = spec.bindings_writer(&probe).generate();
+        return Ok(Answer::Bindings {
+            source: bindings.source,
+            diagnostics: bindings.diagnostics.iter()
+                                 .map(|d| d.to_string()).collect(),
+        });
+    }
+    let probe = options.apply(ProbeBuilder::new()).build().or_else(failure)?;
+    let answer = match command {
+        "size-of" => {
+            probe.size_of(argument).map(|size| Answer::Number(size.to_string()))
+        }
+        "align-of" => {
+            probe.align_of(argument).map(|align| {
+                Answer::Number(align.to_string())
+            })
+        }
+        "constant" => constant_value(&probe, argument).map(Answer::Number),
+        "is-defined" => probe.is_defined_macro(argument).map(Answer::Flag),
+        "has-function" => {
+            probe.has_function(argument).map(|check| {
+                Answer::Function {
+                    declared: check.declared,
+                    links: check.links,
+                }
+            })
+        }
+        _ => { return failure(format!("unknown command `{}`", command)); }
+    };
+    answer.or_else(failure)
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            if message.is_empty() {
+                print!("{}", USAGE);
+                process::exit(EXIT_YES);
+            }
+            eprint!("probe-c-api: {}\n\n{}", message, USAGE);
+            process::exit(EXIT_FAILURE);
+        }
+    };
+    let command = options.command.as_ref().unwrap();
+    let argument = options.argument.as_ref().unwrap();
+    let result = run_command(&options, command, argument);
+    if options.json {
+        let members = match result {
+            Ok(ref answer) => answer.json_members(),
+            Err(ref message) => format!("\"error\": {}", json_string(message)),
+        };
+        println!("{{\"command\": {}, \"argument\": {}, {}}}",
+                 json_string(command), json_string(argument), members);
+    }
+    match result {
+        Ok(answer) => {
+            if !options.json {
+                println!("{}", answer.human());
+            }
+            process::exit(answer.exit_status());
+        }
+        Err(message) => {
+            if !options.json {
+                eprintln!("probe-c-api: {}", message);
+            }
+            process::exit(EXIT_FAILURE);
+        }
+    }
+}
@@ -0,0 +1,536 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generation of Rust source files containing bindings for probed items.
+
+use std::fmt;
+use std::fmt::Write as FormatWrite;
+use std::fs;
+use std::path::Path;
+
+use super::{rust_integer_name, CProbeError, CProbeResult, CTypeKind, Probe};
+use super::CProbeError::*;
+use super::CTypeKind::*;
+use floats::CFloatType;
+use functions::FunctionCheck;
+use layout::rust_identifier;
+use query::{Query, QueryValue};
+use report::{copy_error, ProbeReport, ReportEntry};
+
+/// A C item to be bound (see `BindingsWriter`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BindingItem {
+    /// An integer or floating-point typedef.
+    Typedef(String),
+    /// An integer or floating-point constant.
+    Constant(String),
+    /// A NUL-terminated string constant.
+    StringConstant(String),
+    /// An enum type, with the listed enumerators.
+    Enum {
+        /// The C type, e.g. `enum color`.
+        enum_type: String,
+        /// The name of the Rust type alias.
+        rust_name: String,
+        /// The enumerators to bind.
+        enumerators: Vec<String>,
+    },
+    /// A struct type, with the listed fields.
+    Struct {
+        /// The C type, e.g. `struct point`.
+        struct_type: String,
+        /// The name of the Rust struct.
+        rust_name: String,
+        /// The fields to bind.
+        fields: Vec<String>,
+    },
+    /// A function, which is only checked for (see `Probe::has_function`).
+    Function(String),
+}
+
+impl BindingItem {
+    /// The C name of the item.
+    pub fn c_name(&self) -> &str {
+        match *self {
+            BindingItem::Typedef(ref name) |
+            BindingItem::Constant(ref name) |
+            BindingItem::StringConstant(ref name) |
+            BindingItem::Function(ref name) => name,
+            BindingItem::Enum { ref enum_type, .. } => enum_type,
+            BindingItem::Struct { ref struct_type, .. } => struct_type,
+        }
+    }
+
+    /// What kind of item this is, e.g. `constant`.
+    pub fn description(&self) -> &'static str {
+        match *self {
+            BindingItem::Typedef(..) => "type",
+            BindingItem::Constant(..) => "constant",
+            BindingItem::StringConstant(..) => "string constant",
+            BindingItem::Enum { .. } => "enum",
+            BindingItem::Struct { .. } => "struct",
+            BindingItem::Function(..) => "function",
+        }
+    }
+}
+
+/// A problem that prevented a binding from being generated.
+#[derive(Debug)]
+pub struct BindingDiagnostic {
+    /// The C name of the item.
+    pub c_name: String,
+    /// What kind of item it is, e.g. `constant`.
+    pub kind: &'static str,
+    /// The error encountered while probing it.
+    pub error: CProbeError,
+}
+
+// A one-line summary of an error, for diagnostics. Compiler output is only
+// summarized by its first error message.
+pub(crate) fn error_summary(error: &CProbeError) -> String {
+    match *error {
+        IoError(ref error) => format!("I/O error: {}", error),
+        CompileError(ref failure) => {
+            match failure.first_error() {
+                Some(diagnostic) => {
+                    format!("compilation failed: {}", diagnostic.message)
+                }
+                None => "compilation failed".to_string(),
+            }
+        }
+        RunError(_, ref output) => {
+            format!("probe program failed with {}", output.status)
+        }
+        OtherError(ref message) => message.clone(),
+    }
+}
+
+impl fmt::Display for BindingDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_fmt(format_args!("could not bind {} `{}`: {}",
+                                 self.kind, self.c_name,
+                                 error_summary(&self.error)))
+    }
+}
+
+/// What was found by probing a `BindingItem`, from which its Rust source is
+/// generated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ItemValue {
+    /// The Rust type equivalent to a typedef.
+    Type(String),
+    /// The Rust type of a constant, and its value, written as a Rust
+    /// expression.
+    Constant {
+        /// The Rust type, e.g. `i32`.
+        rust_type: String,
+        /// The value, e.g. `-1` or `f64::INFINITY`.
+        value: String,
+    },
+    /// The contents of a string constant, without the terminating NUL.
+    Bytes(Vec<u8>),
+    /// The Rust representation of an enum type, and the value of each of the
+    /// item's enumerators, in order.
+    Enum {
+        /// The Rust integer type, e.g. `u32`.
+        repr: String,
+        /// The values of the enumerators.
+        values: Vec<i64>,
+    },
+    /// The Rust definition of a struct.
+    Struct(String),
+    /// The result of checking for a function.
+    Function(FunctionCheck),
+}
+
+/// The result of `BindingsWriter::generate`.
+#[derive(Debug)]
+pub struct Bindings {
+    /// The generated Rust source.
+    pub source: String,
+    /// Problems with items that were left out of the source.
+    pub diagnostics: Vec<BindingDiagnostic>,
+}
+
+// The queries that find the kind and size of an arithmetic type.
+fn type_queries(type_: &str) -> Vec<Query> {
+    vec![Query::SizeOf(type_.to_string()),
+         Query::IsFloating(type_.to_string()),
+         Query::IsSigned(type_.to_string())]
+}
+
+// The queries that can be answered in a batch for an item. For a constant,
+// these find the kind and size of its type, and its value both as a signed
+// and as an unsigned integer; only the one that suits the type is used.
+fn item_queries(item: &BindingItem) -> Vec<Query> {
+    match *item {
+        BindingItem::Typedef(ref name) => type_queries(name),
+        BindingItem::Constant(ref name) => {
+            let mut queries = type_queries(&format!("__typeof__({})", name));
+            queries.push(Query::SignedIntegerConstant(name.clone()));
+            queries.push(Query::UnsignedIntegerConstant(name.clone()));
+            queries
+        }
+        _ => Vec::new(),
+    }
+}
+
+// The kind of an arithmetic type, and its Rust equivalent, from the answers
+// to `type_queries`. Types that aren't arithmetic fail to compile for
+// `IsFloating`.
+fn rust_type<I>(type_: &str, answers: &mut I)
+                -> CProbeResult<(CTypeKind, String)>
+    where I: Iterator<Item=CProbeResult<QueryValue>> {
+    let mut next = || answers.next().expect("missing answer to a query");
+    let size = next()?.as_u64().expect("expected an unsigned query");
+    let (is_floating, is_signed) = (next(), next());
+    let kind = match is_floating {
+        Ok(value) => if value.as_bool().expect("expected a boolean query") {
+            CFloat
+        } else {
+            CInteger{
+                signed: is_signed?.as_bool().expect("expected a boolean query")
+            }
+        },
+        Err(CompileError(..)) => CUnknown,
+        Err(error) => { return Err(error); }
+    };
+    let rust_type = match kind {
+        CInteger{ signed } => rust_integer_name(signed, size),
+        CFloat => match size {
+            4 | 8 => Some(format!("f{}", 8 * size)),
+            _ => None,
+        },
+        CUnknown => {
+            return Err(OtherError(format!("`{}` is not an integer or \
+                                           floating-point type",
+                                          type_)));
+        }
+    };
+    match rust_type {
+        Some(rust_type) => Ok((kind, rust_type)),
+        None => Err(OtherError(format!("no Rust type has the size of `{}` \
+                                        ({} bytes)",
+                                       type_, size))),
+    }
+}
+
+// Write a byte string literal.
+fn byte_string_literal(bytes: &[u8]) -> String {
+    let mut literal = "b\"".to_string();
+    for &byte in bytes {
+        for c in std::ascii::escape_default(byte) {
+            literal.push(c as char);
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+// Write a floating-point literal of the given Rust type, which must be `f32`
+// or `f64`.
+fn float_literal(value: f64, rust_type: &str) -> String {
+    if value.is_nan() {
+        format!("{}::NAN", rust_type)
+    } else if value == f64::INFINITY {
+        format!("{}::INFINITY", rust_type)
+    } else if value == f64::NEG_INFINITY {
+        format!("{}::NEG_INFINITY", rust_type)
+    } else if rust_type == "f32" {
+        format!("{:?}", value as f32)
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+/// Writes a Rust module of bindings for declared C items.
+///
+/// Each item is probed when the bindings are generated, and becomes a Rust
+/// item with the C name in its doc comment:
+///
+///  - Typedefs of integer and floating-point types become type aliases, e.g.
+///    `pub type foo_t = i32;`.
+///  - Integer and floating-point constants become `pub const` items with the
+///    Rust equivalent of the constant's C type.
+///  - String constants become byte strings, without the terminating NUL.
+///  - Enums become a type alias for the enum's representation, with a
+///    constant for each enumerator.
+///  - Structs become `#[repr(C)]` structs (see
+///    `Probe::rust_struct_definition`).
+///  - Functions become a `bool` constant named `HAVE_` followed by the
+///    function name, which is true if the function is declared and links.
+///
+/// Items that can't be probed are left out, and reported as diagnostics.
+#[derive(Debug)]
+pub struct BindingsWriter<'p, 'a: 'p> {
+    probe: &'p Probe<'a>,
+    items: Vec<BindingItem>,
+}
+
+impl<'p, 'a> BindingsWriter<'p, 'a> {
+    /// Create a writer that probes items using `probe`.
+    pub fn new(probe: &'p Probe<'a>) -> BindingsWriter<'p, 'a> {
+        BindingsWriter {
+            probe,
+            items: Vec::new(),
+        }
+    }
+
+    /// Declare an integer or floating-point typedef.
+    pub fn typedef(&mut self, name: &str) -> &mut Self {
+        self.items.push(BindingItem::Typedef(name.to_string()));
+        self
+    }
+
+    /// Declare an integer or floating-point constant, such as a macro or a
+    /// `const` global variable.
+    pub fn constant(&mut self, name: &str) -> &mut Self {
+        self.items.push(BindingItem::Constant(name.to_string()));
+        self
+    }
+
+    /// Declare a NUL-terminated string constant.
+    pub fn string_constant(&mut self, name: &str) -> &mut Self {
+        self.items.push(BindingItem::StringConstant(name.to_string()));
+        self
+    }
+
+    /// Declare an enum type, e.g. `enum color`, to be bound as the type alias
+    /// `rust_name`, along with the listed enumerators.
+    pub fn enumeration(&mut self, enum_type: &str, rust_name: &str,
+                       enumerators: &[&str]) -> &mut Self {
+        self.items.push(BindingItem::Enum {
+            enum_type: enum_type.to_string(),
+            rust_name: rust_name.to_string(),
+            enumerators: enumerators.iter().map(|e| e.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Declare a struct type, to be bound as the Rust struct `rust_name` with
+    /// the listed fields.
+    pub fn structure(&mut self, struct_type: &str, rust_name: &str,
+                     fields: &[&str]) -> &mut Self {
+        self.items.push(BindingItem::Struct {
+            struct_type: struct_type.to_string(),
+            rust_name: rust_name.to_string(),
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Declare a function, to be checked for with `Probe::has_function`.
+    pub fn function(&mut self, name: &str) -> &mut Self {
+        self.items.push(BindingItem::Function(name.to_string()));
+        self
+    }
+
+    /// Declare any kind of item.
+    pub fn item(&mut self, item: BindingItem) -> &mut Self {
+        self.items.push(item);
+        self
+    }
+
+    /// Probe every declared item, recording what was found in a report,
+    /// from which the bindings can be generated later.
+    ///
+    /// The types of all typedefs and constants, and the values of integer
+    /// constants, are probed with a single batch (see
+    /// `Probe::evaluate_batch`). Floating-point and 128-bit constants, enums,
+    /// structs, string constants and functions need probe programs of their
+    /// own.
+    pub fn report(&self) -> ProbeReport {
+        let mut report = ProbeReport::new(self.probe);
+        let item_queries: Vec<Vec<Query>> = self.items.iter()
+                                                      .map(item_queries)
+                                                      .collect();
+        let queries: Vec<Query> = item_queries.iter().flatten().cloned()
+                                              .collect();
+        let answers = match self.probe.evaluate_batch(&queries) {
+            Ok(answers) => answers,
+            Err(error) => queries.iter().map(|_| {
+                Err(copy_error(&error))
+            }).collect(),
+        };
+        let mut answers = answers.into_iter();
+        for (item, queries) in self.items.iter().zip(&item_queries) {
+            // An item may stop reading its answers at the first error.
+            let item_answers: Vec<_> = answers.by_ref().take(queries.len())
+                                              .collect();
+            report.entries.push(ReportEntry {
+                item: item.clone(),
+                result: self.probe_item(item, &mut item_answers.into_iter()),
+            });
+        }
+        report
+    }
+
+    /// Probe every declared item, and generate the Rust source for them, in
+    /// the order they were declared.
+    ///
+    /// Items that could not be probed are left out of the source, with a
+    /// comment in their place, and listed in the diagnostics.
+    pub fn generate(&self) -> Bindings {
+        self.report().bindings()
+    }
+
+    /// Generate the bindings, and write the source to `path`. The
+    /// diagnostics are returned.
+    pub fn write_to_file(&self, path: &Path)
+                         -> CProbeResult<Vec<BindingDiagnostic>> {
+        let bindings = self.generate();
+        fs::write(path, bindings.source)?;
+        Ok(bindings.diagnostics)
+    }
+
+    // Probe an item, given the answers to its `item_queries`.
+    fn probe_item<I>(&self, item: &BindingItem, answers: &mut I)
+                     -> CProbeResult<ItemValue>
+        where I: Iterator<Item=CProbeResult<QueryValue>> {
+        match *item {
+            BindingItem::Typedef(ref name) => {
+                rust_type(name, answers).map(|(_, rust_type)| {
+                    ItemValue::Type(rust_type)
+                })
+            }
+            BindingItem::Constant(ref name) => {
+                self.constant_value(name, answers)
+            }
+            BindingItem::StringConstant(ref name) => {
+                self.probe.string_constant(name).map(ItemValue::Bytes)
+            }
+            BindingItem::Enum { ref enum_type, ref enumerators, .. } => {
+                self.enum_value(enum_type, enumerators)
+            }
+            BindingItem::Struct { ref struct_type, ref rust_name,
+                                  ref fields } => {
+                let fields: Vec<&str> = fields.iter().map(|f| &f[..])
+                                                     .collect();
+                self.probe.rust_struct_definition(struct_type, rust_name,
+                                                  &fields)
+                          .map(ItemValue::Struct)
+            }
+            BindingItem::Function(ref name) => {
+                self.probe.has_function(name).map(ItemValue::Function)
+            }
+        }
+    }
+
+    // The value of a constant, given the answers to its `item_queries`.
+    fn constant_value<I>(&self, name: &str, answers: &mut I)
+                         -> CProbeResult<ItemValue>
+        where I: Iterator<Item=CProbeResult<QueryValue>> {
+        let type_ = format!("__typeof__({})", name);
+        let (kind, rust_type) = rust_type(&type_, answers)?;
+        let mut next = || answers.next().expect("missing answer to a query");
+        let (signed_value, unsigned_value) = (next(), next());
+        let value = match kind {
+            CFloat => {
+                let constant = self.probe.double_constant(name)?;
+                if constant.c_type == CFloatType::LongDouble {
+                    return Err(OtherError("long double constants have no \
+                                           Rust equivalent"
+                                          .to_string()));
+                }
+                float_literal(constant.value, &rust_type)
+            }
+            CInteger{ signed: true } => {
+                if rust_type == "i128" {
+                    self.probe.wide_signed_integer_constant(name)?.to_string()
+                } else {
+                    signed_value?.as_i64().expect("expected a signed query")
+                                 .to_string()
+                }
+            }
+            _ => {
+                if rust_type == "u128" {
+                    self.probe.wide_unsigned_integer_constant(name)?
+                              .to_string()
+                } else {
+                    unsigned_value?.as_u64()
+                                   .expect("expected an unsigned query")
+                                   .to_string()
+                }
+            }
+        };
+        Ok(ItemValue::Constant {
+            rust_type,
+            value,
+        })
+    }
+
+    fn enum_value(&self, enum_type: &str, enumerators: &[String])
+                  -> CProbeResult<ItemValue> {
+        let enumerators: Vec<&str> = enumerators.iter().map(|e| &e[..])
+                                                .collect();
+        let info = self.probe.enum_info(enum_type, &enumerators)?;
+        match info.repr {
+            Some(repr) => Ok(ItemValue::Enum {
+                repr,
+                values: info.enumerators.iter()
+                                        .map(|&(_, value)| value)
+                                        .collect(),
+            }),
+            None => Err(OtherError(format!("no Rust integer type has the \
+                                            size of `{}` ({} bytes)",
+                                           enum_type, info.size))),
+        }
+    }
+}
+
+// Generate the Rust source for an item that was probed successfully, or
+// `None` if the value is not of the kind that the item produces.
+pub(crate) fn item_source(item: &BindingItem, value: &ItemValue)
+                          -> Option<String> {
+    let name = item.c_name();
+    match (item, value) {
+        (BindingItem::Typedef(..), ItemValue::Type(rust_type)) => {
+            Some(format!("/// C type `{}`.\npub type {} = {};\n",
+                         name, rust_identifier(name), rust_type))
+        }
+        (BindingItem::Constant(..),
+         ItemValue::Constant { rust_type, value }) => {
+            Some(format!("/// C constant `{}`.\npub const {}: {} = {};\n",
+                         name, rust_identifier(name), rust_type, value))
+        }
+        (BindingItem::StringConstant(..), ItemValue::Bytes(bytes)) => {
+            Some(format!("/// C constant `{}`.\npub const {}: &[u8] = {};\n",
+                         name, rust_identifier(name),
+                         byte_string_literal(bytes)))
+        }
+        (BindingItem::Enum { rust_name, enumerators, .. },
+         ItemValue::Enum { repr, values })
+            if enumerators.len() == values.len() => {
+            let mut source = format!("/// C type `{}`.\npub type {} = {};\n",
+                                     name, rust_name, repr);
+            for (enumerator, value) in enumerators.iter().zip(values) {
+                write!(&mut source,
+                       "/// C enumerator `{}`.\npub const {}: {} = {};\n",
+                       enumerator, rust_identifier(enumerator), rust_name,
+                       value).unwrap();
+            }
+            Some(source)
+        }
+        (BindingItem::Struct { .. }, ItemValue::Struct(definition)) => {
+            Some(format!("/// C type `{}`.\n{}", name, definition))
+        }
+        (BindingItem::Function(..), ItemValue::Function(check)) => {
+            Some(format!("/// Whether C function `{}` is available.\n\
+                          pub const {}: bool = {};\n",
+                         name, rust_identifier(&format!("HAVE_{}", name)),
+                         check.declared && check.links))
+        }
+        _ => None,
+    }
+}
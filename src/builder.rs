@@ -0,0 +1,184 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A builder for `Probe`s that use a `Compiler`.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use super::{EvaluationStrategy, NewProbeError, Probe};
+use compiler::{CompilerOptions, GccCompatible};
+
+/// Builds a `Probe` from compiler settings, rather than closures.
+///
+/// By default, the probe has the same settings as `Probe::default`: it has no
+/// headers, works in an OS-specific temporary directory, uses `gcc`, and runs
+/// each program directly, with the current environment.
+///
+/// ```no_run
+/// use probe_c_api::Probe;
+///
+/// let probe = Probe::builder().define("_GNU_SOURCE")
+///                             .header("<sched.h>")
+///                             .build()
+///                             .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ProbeBuilder {
+    headers: Vec<String>,
+    work_dir: Option<PathBuf>,
+    options: CompilerOptions,
+    program: Option<PathBuf>,
+    use_clang: bool,
+    run_env: Vec<(String, String)>,
+    strategy: Option<EvaluationStrategy>,
+}
+
+impl ProbeBuilder {
+    /// Start building a probe with the default settings.
+    pub fn new() -> ProbeBuilder {
+        ProbeBuilder::default()
+    }
+
+    /// Add a header to include in every program. As with `Probe::new`, the
+    /// header should have the `<>` or `""` delimiters surrounding it.
+    pub fn header(mut self, header: &str) -> ProbeBuilder {
+        self.headers.push(header.to_string());
+        self
+    }
+
+    /// Set the directory where probe programs are written.
+    pub fn work_dir(mut self, work_dir: &Path) -> ProbeBuilder {
+        self.work_dir = Some(work_dir.to_path_buf());
+        self
+    }
+
+    /// Add a directory to search for headers.
+    pub fn include_dir(mut self, dir: &Path) -> ProbeBuilder {
+        self.options.include_dirs.push(dir.to_path_buf());
+        self
+    }
+
+    /// Define a macro on the command line, so that it is defined before any
+    /// header is included.
+    pub fn define(mut self, name: &str) -> ProbeBuilder {
+        self.options.defines.push((name.to_string(), None));
+        self
+    }
+
+    /// Define a macro with a value on the command line.
+    pub fn define_value(mut self, name: &str, value: &str) -> ProbeBuilder {
+        self.options.defines.push((name.to_string(), Some(value.to_string())));
+        self
+    }
+
+    /// Link against a library, given without the `lib` prefix (e.g. `m` for
+    /// `libm`).
+    pub fn library(mut self, library: &str) -> ProbeBuilder {
+        self.options.libraries.push(library.to_string());
+        self
+    }
+
+    /// Add a directory to search for libraries.
+    pub fn library_dir(mut self, dir: &Path) -> ProbeBuilder {
+        self.options.library_dirs.push(dir.to_path_buf());
+        self
+    }
+
+    /// Set the language standard, e.g. `c11`.
+    pub fn std(mut self, std: &str) -> ProbeBuilder {
+        self.options.std = Some(std.to_string());
+        self
+    }
+
+    /// Add any other flag to pass to the compiler.
+    pub fn flag(mut self, flag: &str) -> ProbeBuilder {
+        self.options.flags.push(flag.to_string());
+        self
+    }
+
+    /// Add a flag to pass to the compiler only when linking a program.
+    pub fn link_flag(mut self, flag: &str) -> ProbeBuilder {
+        self.options.link_flags.push(flag.to_string());
+        self
+    }
+
+    /// Set an environment variable for each probe program that is run, e.g.
+    /// `LD_LIBRARY_PATH`.
+    pub fn run_env(mut self, name: &str, value: &str) -> ProbeBuilder {
+        self.run_env.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Use Clang instead of GCC.
+    pub fn clang(mut self) -> ProbeBuilder {
+        self.use_clang = true;
+        self
+    }
+
+    /// Set the command used to run the compiler, e.g. a cross-compiler. By
+    /// default, this is `gcc`, or `clang` if `clang` was called.
+    pub fn compiler_program(mut self, program: &Path) -> ProbeBuilder {
+        self.program = Some(program.to_path_buf());
+        self
+    }
+
+    /// Set the strategy used to answer queries.
+    pub fn evaluation_strategy(mut self, strategy: EvaluationStrategy)
+                               -> ProbeBuilder {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    /// Create the probe.
+    pub fn build(self) -> Result<Probe<'static>, NewProbeError> {
+        let work_dir = self.work_dir.unwrap_or_else(env::temp_dir);
+        let run_env = self.run_env.clone();
+        let run = move |exe_path: &Path| {
+            Command::new(exe_path).envs(run_env.iter().map(|(name, value)| {
+                (name, value)
+            })).output()
+        };
+        let mut compiler = if self.use_clang {
+            GccCompatible::clang(self.options)
+        } else {
+            GccCompatible::gcc(self.options)
+        };
+        if let Some(program) = self.program {
+            compiler.program = program;
+        }
+        let mut probe = Probe::with_compiler_and_run(self.headers, &work_dir,
+                                                     compiler, run)?;
+        // The run environment can affect results too.
+        if let Some(identify) = probe.identify.take() {
+            let run_env = format!("{:?}", self.run_env);
+            probe.identify = Some(Arc::new(move || {
+                format!("{} {}", identify(), run_env)
+            }));
+        }
+        if let Some(strategy) = self.strategy {
+            probe.set_evaluation_strategy(strategy);
+        }
+        Ok(probe)
+    }
+}
+
+impl Probe<'static> {
+    /// Start building a probe (see `ProbeBuilder`).
+    pub fn builder() -> ProbeBuilder {
+        ProbeBuilder::new()
+    }
+}
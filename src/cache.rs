@@ -0,0 +1,371 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A persistent cache of compilation and run results.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use rand::random;
+
+use super::{CProbeResult, Probe};
+use super::CProbeError::*;
+
+// Identifies the format of cache files.
+const CACHE_MAGIC: &[u8] = b"probe-c-api cache 3\n";
+
+// The FNV-1a hash, which is simple and stable across Rust versions, unlike
+// the standard library's hashers.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(bytes);
+    hasher.0
+}
+
+// Exit statuses can only be recreated in a platform-specific way.
+#[cfg(unix)]
+fn status_to_raw(status: &ExitStatus) -> Option<i64> {
+    use std::os::unix::process::ExitStatusExt;
+    Some(i64::from(status.into_raw()))
+}
+
+#[cfg(unix)]
+fn status_from_raw(raw: i64) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(raw as i32)
+}
+
+#[cfg(windows)]
+fn status_to_raw(status: &ExitStatus) -> Option<i64> {
+    status.code().map(|code| i64::from(code as u32))
+}
+
+#[cfg(windows)]
+fn status_from_raw(raw: i64) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(raw as u32)
+}
+
+// Elsewhere, only successes can be cached.
+#[cfg(not(any(unix, windows)))]
+fn status_to_raw(status: &ExitStatus) -> Option<i64> {
+    if status.success() { Some(0) } else { None }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn status_from_raw(_raw: i64) -> ExitStatus {
+    ExitStatus::default()
+}
+
+// Where cached results are kept, and the compiler they came from.
+#[derive(Clone)]
+pub(crate) struct Cache {
+    dir: PathBuf,
+    identity: String,
+}
+
+// The outputs of the commands used to answer a single request, and the
+// contents of any compiled file that was read.
+pub(crate) struct CachedOutputs {
+    pub(crate) outputs: Vec<Output>,
+    pub(crate) contents: Option<Vec<u8>>,
+}
+
+// A file that the source included, with its size, modification time (in
+// nanoseconds since the epoch, or 0 if unknown) and a hash of its contents.
+struct Dependency {
+    path: PathBuf,
+    len: u64,
+    modified: u64,
+    hash: u64,
+}
+
+impl Dependency {
+    fn read(path: PathBuf) -> io::Result<Dependency> {
+        // Check the metadata first, so that a change while the file is being
+        // read makes the entry look stale rather than current.
+        let (len, modified) = file_stamp(&path)?;
+        let hash = hash_bytes(&fs::read(&path)?);
+        Ok(Dependency { path, len, modified, hash })
+    }
+
+    // Check that the file has not changed. The contents are only hashed again
+    // if the size or modification time differ.
+    fn is_current(&self) -> bool {
+        match file_stamp(&self.path) {
+            Ok((len, modified)) if len == self.len && modified != 0 &&
+                                   modified == self.modified => true,
+            Ok(..) => match fs::read(&self.path) {
+                Ok(contents) => hash_bytes(&contents) == self.hash,
+                Err(..) => false,
+            },
+            Err(..) => false,
+        }
+    }
+}
+
+fn file_stamp(path: &Path) -> io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified().ok()
+                           .and_then(|time| time.duration_since(UNIX_EPOCH)
+                                                .ok())
+                           .map_or(0, |time| time.as_nanos() as u64);
+    Ok((metadata.len(), modified))
+}
+
+// A cache file: the full key that it was stored under, the files that the
+// source included, and the cached outputs.
+struct CacheEntry {
+    key: Vec<u8>,
+    dependencies: Vec<Dependency>,
+    outputs: CachedOutputs,
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    fn u64(&mut self) -> Option<u64> {
+        if self.bytes.len() < 8 {
+            return None;
+        }
+        let (value, rest) = self.bytes.split_at(8);
+        self.bytes = rest;
+        let mut array = [0; 8];
+        array.copy_from_slice(value);
+        Some(u64::from_le_bytes(array))
+    }
+
+    fn field(&mut self) -> Option<&'b [u8]> {
+        let len = self.u64()? as usize;
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (value, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(value)
+    }
+}
+
+fn write_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_field(bytes: &mut Vec<u8>, field: &[u8]) {
+    write_u64(bytes, field.len() as u64);
+    bytes.extend_from_slice(field);
+}
+
+impl CacheEntry {
+    // Serialize the entry, unless an exit status can't be stored.
+    fn encode(&self) -> Option<Vec<u8>> {
+        let mut bytes = CACHE_MAGIC.to_vec();
+        write_field(&mut bytes, &self.key);
+        write_u64(&mut bytes, self.dependencies.len() as u64);
+        for dependency in &self.dependencies {
+            write_field(&mut bytes, dependency.path.to_str()?.as_bytes());
+            write_u64(&mut bytes, dependency.len);
+            write_u64(&mut bytes, dependency.modified);
+            write_u64(&mut bytes, dependency.hash);
+        }
+        write_u64(&mut bytes, self.outputs.outputs.len() as u64);
+        for output in &self.outputs.outputs {
+            write_u64(&mut bytes, status_to_raw(&output.status)? as u64);
+            write_field(&mut bytes, &output.stdout);
+            write_field(&mut bytes, &output.stderr);
+        }
+        match self.outputs.contents {
+            Some(ref contents) => {
+                write_u64(&mut bytes, 1);
+                write_field(&mut bytes, contents);
+            }
+            None => { write_u64(&mut bytes, 0); }
+        }
+        Some(bytes)
+    }
+
+    // Deserialize an entry, which must be complete.
+    fn decode(bytes: &[u8]) -> Option<CacheEntry> {
+        let mut reader = Reader {
+            bytes: bytes.strip_prefix(CACHE_MAGIC)?,
+        };
+        let key = reader.field()?.to_vec();
+        let mut dependencies = Vec::new();
+        for _ in 0..reader.u64()? {
+            let path = String::from_utf8(reader.field()?.to_vec()).ok()?;
+            dependencies.push(Dependency {
+                path: PathBuf::from(path),
+                len: reader.u64()?,
+                modified: reader.u64()?,
+                hash: reader.u64()?,
+            });
+        }
+        let mut outputs = Vec::new();
+        for _ in 0..reader.u64()? {
+            outputs.push(Output {
+                status: status_from_raw(reader.u64()? as i64),
+                stdout: reader.field()?.to_vec(),
+                stderr: reader.field()?.to_vec(),
+            });
+        }
+        let contents = match reader.u64()? {
+            0 => None,
+            _ => Some(reader.field()?.to_vec()),
+        };
+        if !reader.bytes.is_empty() {
+            return None;
+        }
+        Some(CacheEntry {
+            key,
+            dependencies,
+            outputs: CachedOutputs { outputs, contents },
+        })
+    }
+
+    // Check that none of the included files have changed.
+    fn is_current(&self) -> bool {
+        self.dependencies.iter().all(Dependency::is_current)
+    }
+}
+
+impl<'a> Probe<'a> {
+    /// Cache the results of compiling and running probe programs in a
+    /// subdirectory of `OUT_DIR`, if that environment variable is set (as it
+    /// is for Cargo build scripts), or else of the work directory.
+    ///
+    /// See `enable_cache_in` for details.
+    pub fn enable_cache(&mut self) -> CProbeResult<()> {
+        let base = match env::var_os("OUT_DIR") {
+            Some(out_dir) => PathBuf::from(out_dir),
+            None => self.work_dir.clone(),
+        };
+        self.enable_cache_in(&base.join("probe-c-api-cache"))
+    }
+
+    /// Cache the results of compiling and running probe programs in `dir`,
+    /// which is created if necessary. This persists across probes and
+    /// processes, so that later builds can skip compiling and running the
+    /// same programs.
+    ///
+    /// Each result is keyed by the generated source and the compiler
+    /// identity (see `set_compiler_identity`). Entries are stored under a
+    /// hash of that key, but record the full key, so an entry is only used
+    /// if it matches exactly; entries that don't match, or can't be read,
+    /// are recomputed and overwritten. The contents of every
+    /// file that the source included are recorded too, so the result is not
+    /// used if any of those files have changed. Finding those files requires
+    /// a preprocess command, so this returns an error if the probe has none
+    /// (see `set_preprocess`). Results are not cached if the source can't be
+    /// preprocessed, or an included file can't be read.
+    pub fn enable_cache_in(&mut self, dir: &Path) -> CProbeResult<()> {
+        if self.preprocess.is_none() {
+            return Err(OtherError("the cache requires a preprocess command"
+                                      .to_string()));
+        }
+        fs::create_dir_all(dir)?;
+        let identity = match self.identify {
+            Some(ref identify) => (**identify)(),
+            None => String::new(),
+        };
+        self.cache = Some(Cache {
+            dir: dir.to_path_buf(),
+            identity,
+        });
+        Ok(())
+    }
+
+    /// Stop using the cache.
+    pub fn disable_cache(&mut self) {
+        self.cache = None;
+    }
+
+    /// Set a string identifying the compiler, its flags, and anything else
+    /// that affects compilation or run results, for use in cache keys.
+    ///
+    /// A probe created with `Probe::with_compiler` or `ProbeBuilder` gets an
+    /// identity from its `Compiler`. Probes created with `Probe::new` have an
+    /// empty identity by default, so this should be set if the compile or
+    /// run commands may change. This also applies to a cache that is already
+    /// enabled.
+    pub fn set_compiler_identity(&mut self, identity: &str) {
+        if let Some(ref mut cache) = self.cache {
+            cache.identity = identity.to_string();
+        }
+        let identity = identity.to_string();
+        self.identify = Some(Arc::new(move || identity.clone()));
+    }
+
+    // Get outputs for a request of the given kind, from the cache if possible,
+    // otherwise from `compute`.
+    pub(crate) fn cached<F>(&self, kind: &str, source: &str, compute: F)
+                            -> io::Result<CachedOutputs>
+        where F: FnOnce() -> io::Result<CachedOutputs> {
+        let cache = match self.cache {
+            Some(ref cache) => cache,
+            None => { return compute(); }
+        };
+        let mut key = Vec::new();
+        write_field(&mut key, kind.as_bytes());
+        write_field(&mut key, cache.identity.as_bytes());
+        write_field(&mut key, source.as_bytes());
+        let path = cache.dir.join(format!("{:016x}", hash_bytes(&key)));
+        if let Ok(bytes) = fs::read(&path) {
+            if let Some(entry) = CacheEntry::decode(&bytes) {
+                if entry.key == key && entry.is_current() {
+                    return Ok(entry.outputs);
+                }
+            }
+        }
+        let outputs = compute()?;
+        let files = match self.files_included_by(source) {
+            Ok(files) => files,
+            Err(..) => { return Ok(outputs); }
+        };
+        let dependencies = match files.into_iter().map(Dependency::read)
+                                      .collect::<io::Result<Vec<_>>>() {
+            Ok(dependencies) => dependencies,
+            Err(..) => { return Ok(outputs); }
+        };
+        let entry = CacheEntry { key, dependencies, outputs };
+        if let Some(bytes) = entry.encode() {
+            // Write to a temporary file first, so that other processes never
+            // see a partial entry.
+            let temporary = cache.dir.join(format!("tmp-{}", random::<u64>()));
+            fs::write(&temporary, bytes)?;
+            fs::rename(&temporary, &path)?;
+        }
+        Ok(entry.outputs)
+    }
+}
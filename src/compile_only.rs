@@ -0,0 +1,169 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Evaluation of queries by compilation alone, for use when probe programs
+//! cannot be run (see `EvaluationStrategy::CompileOnly`).
+
+use super::{CProbeResult, Probe};
+use super::CProbeError::*;
+use diagnostics::compile_error;
+use query::{query_headers, Query, QueryValue, ValueKind};
+
+// Write an integer as a C literal of type `long long` or `unsigned long long`.
+fn c_literal(value: i128, signed: bool) -> String {
+    if !signed {
+        format!("{}ULL", value)
+    } else if value == i128::from(i64::MIN) {
+        // The most negative value can't be written as the negation of a
+        // positive literal.
+        format!("(-{}LL - 1)", i64::MAX)
+    } else if value < 0 {
+        format!("(-{}LL)", -value)
+    } else {
+        format!("{}LL", value)
+    }
+}
+
+impl<'a> Probe<'a> {
+    // Evaluate a query using only the `compile_to` command.
+    pub(crate) fn evaluate_at_compile_time(&self, query: &Query)
+                                           -> CProbeResult<QueryValue> {
+        let headers = query_headers(std::slice::from_ref(query));
+        let prelude = query.prelude(0);
+        let expression = query.expression(0);
+        match query.value_kind() {
+            ValueKind::Bool => {
+                self.check_constant_expression(&headers, &prelude,
+                                               &expression)?;
+                self.assertion_holds(&headers, &prelude, &expression)
+                    .map(QueryValue::Bool)
+            }
+            ValueKind::Signed => {
+                let expression = format!("((long long) ({}))", expression);
+                self.compile_time_integer(&headers, &prelude, &expression,
+                                          true)
+                    .map(|value| QueryValue::Signed(value as i64))
+            }
+            ValueKind::Unsigned => {
+                let expression = format!("((unsigned long long) ({}))",
+                                         expression);
+                self.compile_time_integer(&headers, &prelude, &expression,
+                                          false)
+                    .map(|value| QueryValue::Unsigned(value as u64))
+            }
+        }
+    }
+
+    // Source for a program that only compiles if `condition` is true.
+    fn static_assertion_source(&self, headers: &[&str], prelude: &str,
+                               condition: &str) -> String {
+        format!("{}\n\
+                 {}\
+                 typedef char probe_c_api_assertion[({}) ? 1 : -1];\n\
+                 int main(void) {{ return 0; }}\n",
+                self.header_includes(headers),
+                prelude,
+                condition)
+    }
+
+    // Check whether a condition holds, by seeing if a static assertion of it
+    // compiles.
+    fn assertion_holds(&self, headers: &[&str], prelude: &str,
+                       condition: &str) -> CProbeResult<bool> {
+        let source = self.static_assertion_source(headers, prelude, condition);
+        Ok(self.check_compile(&source)?.status.success())
+    }
+
+    // Since a failed static assertion looks just like any other compilation
+    // error, make sure that the expression is usable before relying on it.
+    // Compilers may quietly accept a non-constant array size as an extension,
+    // so we check the expression by using it as a case label instead.
+    fn check_constant_expression(&self, headers: &[&str], prelude: &str,
+                                 expression: &str) -> CProbeResult<()> {
+        let source = format!("{}\n\
+                              {}\
+                              int main(void) {{\n\
+                              switch (0) {{ case ({}): break; }}\n\
+                              return 0;\n\
+                              }}\n",
+                             self.header_includes(headers),
+                             prelude,
+                             expression);
+        let compile_output = self.check_compile(&source)?;
+        if compile_output.status.success() {
+            Ok(())
+        } else {
+            Err(compile_error(compile_output, &source))
+        }
+    }
+
+    // Find the value of an integer constant expression of type `long long` (if
+    // `signed`) or `unsigned long long`. We first bracket the value by
+    // doubling the size of the search range, then do a binary search.
+    fn compile_time_integer(&self, headers: &[&str], prelude: &str,
+                            expression: &str, signed: bool)
+                            -> CProbeResult<i128> {
+        self.check_constant_expression(headers, prelude, expression)?;
+        let (min, max) = if signed {
+            (i128::from(i64::MIN), i128::from(i64::MAX))
+        } else {
+            (0, i128::from(u64::MAX))
+        };
+        let at_most = |value| {
+            format!("{} <= {}", expression, c_literal(value, signed))
+        };
+        let (mut low, mut high);
+        let negative_condition = format!("{} < 0", expression);
+        if signed && self.assertion_holds(headers, prelude,
+                                          &negative_condition)? {
+            high = -1;
+            low = -1;
+            loop {
+                let at_least = format!("{} >= {}", expression,
+                                       c_literal(low, signed));
+                if self.assertion_holds(headers, prelude, &at_least)? {
+                    break;
+                }
+                if low == min {
+                    return Err(OtherError("could not find a lower bound for \
+                                           a compile-time constant"
+                                          .to_string()));
+                }
+                high = low - 1;
+                low = (low * 2).max(min);
+            }
+        } else {
+            low = 0;
+            high = 0;
+            while !self.assertion_holds(headers, prelude, &at_most(high))? {
+                if high == max {
+                    return Err(OtherError("could not find an upper bound for \
+                                           a compile-time constant"
+                                          .to_string()));
+                }
+                low = high + 1;
+                high = (high * 2 + 1).min(max);
+            }
+        }
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.assertion_holds(headers, prelude, &at_most(middle))? {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        Ok(low)
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed descriptions of C compilers, as an alternative to raw closures.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::CommandResult;
+use preprocess::PreprocessMode;
+
+/// The kind of file that a compilation should produce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputKind {
+    /// A linked, runnable program.
+    Executable,
+    /// An object file, which is not linked (`cc -c`).
+    Object,
+}
+
+/// Settings used for every compilation by a `Compiler`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompilerOptions {
+    /// Directories searched for headers (`-I`).
+    pub include_dirs: Vec<PathBuf>,
+    /// Preprocessor definitions (`-D`), each with an optional value.
+    pub defines: Vec<(String, Option<String>)>,
+    /// Libraries to link against (`-l`), without the `lib` prefix.
+    pub libraries: Vec<String>,
+    /// Directories searched for libraries (`-L`).
+    pub library_dirs: Vec<PathBuf>,
+    /// The language standard (`-std`), e.g. `c11`.
+    pub std: Option<String>,
+    /// Any other flags, passed before the source file.
+    pub flags: Vec<String>,
+    /// Flags passed only when linking a program, e.g. `-pthread` or
+    /// `-Wl,...`. They are passed in order, after `libraries`, so this can
+    /// also hold libraries whose position among other flags matters.
+    pub link_flags: Vec<String>,
+}
+
+/// A C compiler that can be used by a `Probe` (see `Probe::with_compiler`).
+pub trait Compiler {
+    /// Compile the source file at `source`, writing a file of the given
+    /// `kind` to `output`.
+    fn compile(&self, source: &Path, output: &Path, kind: OutputKind)
+               -> CommandResult;
+
+    /// Run only the preprocessor on the source file at `source`, yielding the
+    /// requested output on standard output.
+    fn preprocess(&self, source: &Path, mode: PreprocessMode)
+                  -> CommandResult;
+
+    /// A string that identifies the compiler and its settings, used to key
+    /// cached results (see `Probe::enable_cache_in`). Results from compilers
+    /// with different identities are never confused.
+    ///
+    /// The default is an empty string, which is only suitable if the compiler
+    /// never changes.
+    fn identity(&self) -> String {
+        String::new()
+    }
+}
+
+/// A C compiler with a GCC-compatible command line, such as GCC itself or
+/// Clang.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GccCompatible {
+    /// The command used to run the compiler, e.g. `gcc` or `clang`.
+    pub program: PathBuf,
+    /// Settings used for every compilation.
+    pub options: CompilerOptions,
+}
+
+impl GccCompatible {
+    /// Use the compiler run by `program` with the given options.
+    pub fn new(program: &Path, options: CompilerOptions) -> GccCompatible {
+        GccCompatible {
+            program: program.to_path_buf(),
+            options,
+        }
+    }
+
+    /// Use `gcc` with the given options.
+    pub fn gcc(options: CompilerOptions) -> GccCompatible {
+        GccCompatible::new(Path::new("gcc"), options)
+    }
+
+    /// Use `clang` with the given options.
+    pub fn clang(options: CompilerOptions) -> GccCompatible {
+        GccCompatible::new(Path::new("clang"), options)
+    }
+
+    // Start a command with everything but the inputs and outputs.
+    fn command(&self) -> Command {
+        let options = &self.options;
+        let mut command = Command::new(&self.program);
+        // Diagnostics are classified by their English messages (see
+        // `CompileFailure::kind`).
+        command.env("LC_ALL", "C");
+        if let Some(std) = &options.std {
+            command.arg(format!("-std={}", std));
+        }
+        for dir in &options.include_dirs {
+            command.arg("-I").arg(dir);
+        }
+        for (name, value) in &options.defines {
+            match value {
+                Some(value) => command.arg(format!("-D{}={}", name, value)),
+                None => command.arg(format!("-D{}", name)),
+            };
+        }
+        command.args(&options.flags);
+        command
+    }
+}
+
+/// Uses `gcc` with default options.
+impl Default for GccCompatible {
+    fn default() -> Self {
+        GccCompatible::gcc(CompilerOptions::default())
+    }
+}
+
+impl Compiler for GccCompatible {
+    fn compile(&self, source: &Path, output: &Path, kind: OutputKind)
+               -> CommandResult {
+        let mut command = self.command();
+        if kind == OutputKind::Object {
+            command.arg("-c");
+        }
+        command.arg(source).arg("-o").arg(output);
+        // Libraries must come after the source file that uses them.
+        if kind == OutputKind::Executable {
+            for dir in &self.options.library_dirs {
+                command.arg("-L").arg(dir);
+            }
+            for library in &self.options.libraries {
+                command.arg(format!("-l{}", library));
+            }
+            command.args(&self.options.link_flags);
+        }
+        command.output()
+    }
+
+    fn preprocess(&self, source: &Path, mode: PreprocessMode)
+                  -> CommandResult {
+        let mut command = self.command();
+        if mode == PreprocessMode::DefinedMacros {
+            command.arg("-dM");
+        }
+        command.arg("-E").arg(source).output()
+    }
+
+    // Identify the compiler by its command, settings and version message.
+    fn identity(&self) -> String {
+        let version = match Command::new(&self.program).arg("--version")
+                                                       .output() {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+            Err(..) => String::new(),
+        };
+        format!("{:?} {:?} {}", self.program, self.options, version)
+    }
+}
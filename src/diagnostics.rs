@@ -0,0 +1,232 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing and classification of compiler diagnostics.
+
+use std::fmt;
+use std::path::Path;
+use std::process;
+
+use super::{output_as_string, CProbeError};
+use super::CProbeError::*;
+
+/// How serious a compiler diagnostic is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// An error (including a fatal error), which stops compilation.
+    Error,
+    /// A warning.
+    Warning,
+    /// A note, which adds detail to the diagnostic before it.
+    Note,
+}
+
+/// A single diagnostic printed by a compiler, in the format used by GCC and
+/// Clang (`file:line:column: severity: message`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompilerDiagnostic {
+    /// The file the diagnostic refers to. This may also be the name of a tool,
+    /// e.g. `collect2` for an error reported when linking.
+    pub file: String,
+    /// The line number, starting at 1, if one was given.
+    pub line: Option<u64>,
+    /// The column number, starting at 1, if one was given.
+    pub column: Option<u64>,
+    /// How serious the diagnostic is.
+    pub severity: Severity,
+    /// The message, e.g. `unknown type name 'foo_t'`.
+    pub message: String,
+    /// If the diagnostic refers to the generated probe program, the line of
+    /// the program that it refers to.
+    pub source_line: Option<String>,
+}
+
+/// What caused a compilation to fail, as far as can be told from the first
+/// error message (see `CompileFailure::kind`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompileErrorKind {
+    /// An identifier (the string) was not declared, e.g. because a macro or
+    /// function is missing.
+    UndeclaredIdentifier(String),
+    /// A type name (the string) was not declared.
+    UnknownTypeName(String),
+    /// A type is incomplete, e.g. a struct that was declared but not defined.
+    /// The string is the last name quoted in the compiler's message, which is
+    /// normally the type, but may be the field or variable with that type.
+    IncompleteType(String),
+    /// Any other failure, or one whose message was not recognized.
+    Other,
+}
+
+/// Details of a failed compilation.
+#[derive(Clone)]
+pub struct CompileFailure {
+    /// The output of the compiler.
+    pub output: process::Output,
+    /// The source that failed to compile.
+    pub source: String,
+    /// The diagnostics that the compiler printed on standard error, in order.
+    pub diagnostics: Vec<CompilerDiagnostic>,
+}
+
+impl fmt::Debug for CompileFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_fmt(
+            format_args!("probe_c_api::CompileFailure{{ output: {}, \
+                          diagnostics: {:?} }}",
+                         output_as_string(&self.output),
+                         self.diagnostics)
+        )
+    }
+}
+
+impl CompileFailure {
+    /// The first error reported by the compiler, if any could be parsed.
+    pub fn first_error(&self) -> Option<&CompilerDiagnostic> {
+        self.diagnostics.iter().find(|diagnostic| {
+            diagnostic.severity == Severity::Error
+        })
+    }
+
+    /// Classify the first error reported by the compiler.
+    ///
+    /// This recognizes the English messages of GCC and Clang. `GccCompatible`
+    /// runs the compiler in the C locale, so that its messages are not
+    /// translated. Probes created with `Probe::new` run whatever command the
+    /// caller gives, which must set `LC_ALL=C` itself, or failures may be
+    /// classified as `Other`.
+    pub fn kind(&self) -> CompileErrorKind {
+        let message = match self.first_error() {
+            Some(error) => &error.message[..],
+            None => { return CompileErrorKind::Other; }
+        };
+        let names = quoted_names(message);
+        // Messages about incomplete types may quote other things first, e.g.
+        // `invalid application of 'sizeof' to incomplete type 'struct foo'`.
+        let (first, last) = match (names.first(), names.last()) {
+            (Some(first), Some(last)) => (first.clone(), last.clone()),
+            _ => { return CompileErrorKind::Other; }
+        };
+        if message.contains("undeclared") ||
+           message.starts_with("implicit declaration of function") {
+            CompileErrorKind::UndeclaredIdentifier(first)
+        } else if message.starts_with("unknown type name") {
+            CompileErrorKind::UnknownTypeName(first)
+        } else if message.contains("incomplete type") ||
+                  (message.starts_with("storage size of") &&
+                   message.ends_with("isn't known")) {
+            CompileErrorKind::IncompleteType(last)
+        } else {
+            CompileErrorKind::Other
+        }
+    }
+}
+
+// Whether a file name is that of a generated probe program (see
+// `Probe::random_source_and_exe_paths`).
+fn is_probe_source(file: &str) -> bool {
+    match Path::new(file).file_name().and_then(|name| name.to_str()) {
+        Some(name) => name.starts_with("source-") && name.ends_with(".c"),
+        None => false,
+    }
+}
+
+// Split a trailing `:number` off of `location`.
+fn split_number(location: &str) -> Option<(&str, u64)> {
+    let colon = location.rfind(':')?;
+    let number = location[colon + 1..].parse().ok()?;
+    Some((&location[..colon], number))
+}
+
+// Parse one line of compiler output, if it is a diagnostic.
+fn parse_diagnostic(line: &str, source: &str) -> Option<CompilerDiagnostic> {
+    let markers = [(": fatal error: ", Severity::Error),
+                   (": error: ", Severity::Error),
+                   (": warning: ", Severity::Warning),
+                   (": note: ", Severity::Note)];
+    let (start, marker, severity) = markers.iter().filter_map(|marker| {
+        line.find(marker.0).map(|start| (start, marker.0, marker.1))
+    }).min_by_key(|&(start, _, _)| start)?;
+    let location = &line[..start];
+    let (location, column, line_number) = match split_number(location) {
+        Some((rest, last)) => match split_number(rest) {
+            Some((file, line_number)) => {
+                (file, Some(last), Some(line_number))
+            }
+            None => (rest, None, Some(last)),
+        },
+        None => (location, None, None),
+    };
+    let source_line = match line_number {
+        Some(line_number) if line_number > 0 &&
+                             is_probe_source(location) => {
+            source.lines().nth(line_number as usize - 1)
+                  .map(|line| line.to_string())
+        }
+        _ => None,
+    };
+    Some(CompilerDiagnostic {
+        file: location.to_string(),
+        line: line_number,
+        column,
+        severity,
+        message: line[start + marker.len()..].trim().to_string(),
+        source_line,
+    })
+}
+
+/// Parse the diagnostics in a compiler's standard error. Lines that are not
+/// diagnostics, such as quoted source lines, are skipped.
+///
+/// Diagnostics that refer to a file named like a generated probe program are
+/// matched to the line of `source` that they refer to.
+pub fn parse_diagnostics(stderr: &str, source: &str)
+                         -> Vec<CompilerDiagnostic> {
+    stderr.lines()
+          .filter_map(|line| parse_diagnostic(line, source))
+          .collect()
+}
+
+// The names quoted in a message. GCC uses typographic quotes, unless the
+// locale prevents it.
+fn quoted_names(message: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut current: Option<String> = None;
+    for c in message.chars() {
+        let opening = c == '\'' || c == '\u{2018}' || c == '`';
+        let closing = c == '\'' || c == '\u{2019}';
+        match current {
+            Some(ref mut name) if !closing => { name.push(c); }
+            Some(..) => { names.extend(current.take()); }
+            None if opening => { current = Some(String::new()); }
+            None => {}
+        }
+    }
+    names
+}
+
+/// Turn the output of a failed compilation into an error, with the
+/// diagnostics parsed out of it.
+pub(crate) fn compile_error(output: process::Output, source: &str)
+                            -> CProbeError {
+    let diagnostics = {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        parse_diagnostics(&stderr, source)
+    };
+    CompileError(Box::new(CompileFailure {
+        output,
+        source: source.to_string(),
+        diagnostics,
+    }))
+}
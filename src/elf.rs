@@ -0,0 +1,211 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Just enough of an ELF reader to find the initial contents of global
+//! variables in an object file or executable.
+
+use std::convert::TryFrom;
+
+use Endianness;
+
+// Section types that we care about.
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+
+// Special section indices below this are real sections.
+const SHN_LORESERVE: u16 = 0xff00;
+
+// Offsets and sizes come from the file, so arithmetic on them must not
+// overflow or be truncated, even if the file is malformed.
+fn checked_offset(base: u64, index: u64, size: u64) -> Result<u64, String> {
+    index.checked_mul(size).and_then(|offset| base.checked_add(offset))
+         .ok_or_else(|| "ELF file has an offset out of range".to_string())
+}
+
+fn to_usize(value: u64) -> Result<usize, String> {
+    usize::try_from(value).map_err(|_| {
+        "ELF file has an offset out of range".to_string()
+    })
+}
+
+struct Section {
+    type_: u32,
+    addr: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    entsize: u64,
+}
+
+// A parsed ELF file, borrowing the underlying bytes.
+pub(crate) struct ElfFile<'b> {
+    bytes: &'b [u8],
+    pub(crate) endianness: Endianness,
+    pub(crate) is_64_bit: bool,
+    sections: Vec<Section>,
+}
+
+impl<'b> ElfFile<'b> {
+    pub(crate) fn parse(bytes: &'b [u8]) -> Result<ElfFile<'b>, String> {
+        if bytes.len() < 16 || &bytes[0..4] != b"\x7fELF" {
+            return Err("file is not in ELF format".to_string());
+        }
+        let is_64_bit = match bytes[4] {
+            1 => false,
+            2 => true,
+            class => {
+                return Err(format!("unknown ELF class {}", class));
+            }
+        };
+        let endianness = match bytes[5] {
+            1 => Endianness::Little,
+            2 => Endianness::Big,
+            data => {
+                return Err(format!("unknown ELF data encoding {}", data));
+            }
+        };
+        let mut elf = ElfFile {
+            bytes,
+            endianness,
+            is_64_bit,
+            sections: Vec::new(),
+        };
+        let (shoff, shentsize, shnum) = if is_64_bit {
+            (elf.read_uint(0x28, 8)?, elf.read_uint(0x3a, 2)?,
+             elf.read_uint(0x3c, 2)?)
+        } else {
+            (elf.read_uint(0x20, 4)?, elf.read_uint(0x2e, 2)?,
+             elf.read_uint(0x30, 2)?)
+        };
+        for i in 0..shnum {
+            let header = checked_offset(shoff, i, shentsize)?;
+            let field = |offset, len| {
+                elf.read_uint(checked_offset(header, 1, offset)?, len)
+            };
+            let section = if is_64_bit {
+                Section {
+                    type_: field(4, 4)? as u32,
+                    addr: field(16, 8)?,
+                    offset: field(24, 8)?,
+                    size: field(32, 8)?,
+                    link: field(40, 4)? as u32,
+                    entsize: field(56, 8)?,
+                }
+            } else {
+                Section {
+                    type_: field(4, 4)? as u32,
+                    addr: field(12, 4)?,
+                    offset: field(16, 4)?,
+                    size: field(20, 4)?,
+                    link: field(24, 4)? as u32,
+                    entsize: field(36, 4)?,
+                }
+            };
+            elf.sections.push(section);
+        }
+        Ok(elf)
+    }
+
+    fn slice(&self, offset: u64, len: u64) -> Result<&'b [u8], String> {
+        let start = to_usize(offset)?;
+        let end = start.checked_add(to_usize(len)?);
+        match end {
+            Some(end) if end <= self.bytes.len() => Ok(&self.bytes[start..end]),
+            _ => Err("ELF file is truncated".to_string()),
+        }
+    }
+
+    // Read an unsigned integer of `len` bytes, in the file's byte order.
+    pub(crate) fn read_uint(&self, offset: u64, len: u64)
+                            -> Result<u64, String> {
+        Ok(self.endianness.decode(self.slice(offset, len)?))
+    }
+
+    // Read a NUL-terminated string.
+    fn read_str(&self, offset: u64) -> Result<&'b [u8], String> {
+        let start = to_usize(offset)?;
+        if start > self.bytes.len() {
+            return Err("ELF file is truncated".to_string());
+        }
+        let rest = &self.bytes[start..];
+        match rest.iter().position(|&byte| byte == 0) {
+            Some(len) => Ok(&rest[..len]),
+            None => Err("unterminated string in ELF file".to_string()),
+        }
+    }
+
+    // Find the initial contents of the global variable `name`.
+    pub(crate) fn symbol_contents(&self, name: &str)
+                                  -> Result<Vec<u8>, String> {
+        let symtab = match self.sections.iter()
+                                        .find(|s| s.type_ == SHT_SYMTAB) {
+            Some(section) => section,
+            None => {
+                return Err("ELF file has no symbol table".to_string());
+            }
+        };
+        let strtab = match self.sections.get(symtab.link as usize) {
+            Some(section) => section,
+            None => {
+                return Err("ELF symbol table has no string table".to_string());
+            }
+        };
+        let entsize = if symtab.entsize != 0 {
+            symtab.entsize
+        } else if self.is_64_bit {
+            24
+        } else {
+            16
+        };
+        for i in 0..symtab.size / entsize {
+            let entry = checked_offset(symtab.offset, i, entsize)?;
+            let field = |offset, len| {
+                self.read_uint(checked_offset(entry, 1, offset)?, len)
+            };
+            let (st_name, st_shndx, st_value, st_size) = if self.is_64_bit {
+                (field(0, 4)?, field(6, 2)? as u16, field(8, 8)?,
+                 field(16, 8)?)
+            } else {
+                (field(0, 4)?, field(14, 2)? as u16, field(4, 4)?,
+                 field(8, 4)?)
+            };
+            let name_offset = checked_offset(strtab.offset, 1, st_name)?;
+            if self.read_str(name_offset)? != name.as_bytes() {
+                continue;
+            }
+            if st_shndx == 0 || st_shndx >= SHN_LORESERVE {
+                return Err(format!("symbol {} is not defined in a section",
+                                   name));
+            }
+            let section = match self.sections.get(st_shndx as usize) {
+                Some(section) => section,
+                None => {
+                    return Err(format!("symbol {} has a bad section index",
+                                       name));
+                }
+            };
+            // Zero-initialized data takes up no space in the file.
+            if section.type_ == SHT_NOBITS {
+                return Ok(vec![0; to_usize(st_size)?]);
+            }
+            // In executables, the symbol value is an address, while in
+            // relocatable objects it is an offset into a section whose address
+            // is zero, so this works for both.
+            let offset = st_value.wrapping_sub(section.addr)
+                                 .wrapping_add(section.offset);
+            return self.slice(offset, st_size).map(|slice| slice.to_vec());
+        }
+        Err(format!("symbol {} not found", name))
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Probing of enumeration types.
+
+use super::{rust_integer_name, CProbeResult, Probe};
+use super::CProbeError::*;
+use query::Query;
+
+/// Information about a C enumeration type and its enumerators.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnumInfo {
+    /// Each enumerator that was asked about, with its value, in the order
+    /// requested.
+    pub enumerators: Vec<(String, i64)>,
+    /// Size of the enumeration type, in bytes.
+    pub size: u64,
+    /// Whether the enumeration type is signed.
+    pub signed: bool,
+    /// The Rust integer type to use in `#[repr(..)]` for an equivalent Rust
+    /// enum, or `None` if no Rust integer type has the right size.
+    pub repr: Option<String>,
+    /// Groups of enumerators that share the same value. A plain Rust enum
+    /// can't represent these, since each variant needs its own discriminant.
+    pub duplicates: Vec<Vec<String>>,
+    /// Whether the distinct values form a single range with no gaps.
+    pub contiguous: bool,
+}
+
+impl<'a> Probe<'a> {
+    /// Get the values of a list of enumerators, along with the size and
+    /// signedness of the enumeration type.
+    ///
+    /// The size and signedness are probed in one batch, then the values in
+    /// another, read as signed or unsigned to match the enumeration type.
+    /// Standard C limits enumerators to the range of `int`, but compilers
+    /// such as GCC accept values up to the range of `unsigned long long`, and
+    /// an `OtherError` is returned for an unsigned value that doesn't fit in
+    /// an `i64`. If any other part of the information cannot be probed, the
+    /// first error encountered is returned.
+    pub fn enum_info(&self, enum_type: &str, enumerators: &[&str])
+                     -> CProbeResult<EnumInfo> {
+        let queries = [Query::SizeOf(enum_type.to_string()),
+                       Query::IsSigned(enum_type.to_string())];
+        let mut results = self.evaluate_batch(&queries)?.into_iter();
+        let size = results.next().unwrap()?
+                          .as_u64().expect("expected an unsigned query");
+        let signed = results.next().unwrap()?
+                            .as_bool().expect("expected a boolean query");
+        let queries: Vec<Query> = enumerators.iter().map(|enumerator| {
+            if signed {
+                Query::SignedIntegerConstant(enumerator.to_string())
+            } else {
+                Query::UnsignedIntegerConstant(enumerator.to_string())
+            }
+        }).collect();
+        let results = self.evaluate_batch(&queries)?;
+        let mut values = Vec::with_capacity(enumerators.len());
+        for (enumerator, result) in enumerators.iter().zip(results) {
+            let value = result?;
+            let value = match value.as_i64() {
+                Some(value) => value,
+                None => {
+                    let value = value.as_u64()
+                                     .expect("expected an unsigned query");
+                    if value > i64::MAX as u64 {
+                        return Err(OtherError(format!(
+                            "value of enumerator {} does not fit in an i64",
+                            enumerator)));
+                    }
+                    value as i64
+                }
+            };
+            values.push((enumerator.to_string(), value));
+        }
+        let mut duplicates: Vec<Vec<String>> = Vec::new();
+        for (i, &(_, value)) in values.iter().enumerate() {
+            // Only start a group at the first enumerator with each value.
+            if values[..i].iter().any(|&(_, other)| other == value) {
+                continue;
+            }
+            let group: Vec<String> =
+                values[i..].iter()
+                           .filter(|&&(_, other)| other == value)
+                           .map(|(other_name, _)| other_name.clone())
+                           .collect();
+            if group.len() > 1 {
+                duplicates.push(group);
+            }
+        }
+        let mut distinct: Vec<i64> =
+            values.iter().map(|&(_, value)| value).collect();
+        distinct.sort();
+        distinct.dedup();
+        let contiguous = distinct.windows(2).all(|pair| pair[1] == pair[0] + 1);
+        Ok(EnumInfo {
+            enumerators: values,
+            size,
+            signed,
+            repr: rust_integer_name(signed, size),
+            duplicates,
+            contiguous,
+        })
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks for the existence of headers, types and struct members.
+
+use super::{CProbeResult, Probe};
+use super::diagnostics::compile_error;
+
+impl<'a> Probe<'a> {
+    // Check that a trivial program, using only the probe's own headers,
+    // compiles. If it doesn't, then any other compilation failure says nothing
+    // about the item being checked, so the control program's output is
+    // returned as a compilation error.
+    fn check_control_program(&self) -> CProbeResult<()> {
+        let source = self.main_source_template(vec![], "return 0;");
+        let compile_output = self.check_compile(&source)?;
+        if compile_output.status.success() {
+            Ok(())
+        } else {
+            Err(compile_error(compile_output, &source))
+        }
+    }
+
+    // Check that the control program compiles, then check whether a program
+    // with the given extra headers and `main` body compiles.
+    fn compiles_after_control(&self, headers: Vec<&str>, main_body: &str)
+                              -> CProbeResult<bool> {
+        self.check_control_program()?;
+        let source = self.main_source_template(headers, main_body);
+        Ok(self.check_compile(&source)?.status.success())
+    }
+
+    /// Check whether a header can be included, in addition to the probe's own
+    /// headers. The header should have the `<>` or `""` delimiters around it.
+    ///
+    /// Returns `Ok(false)` if the header is missing (or fails to compile). If
+    /// even a trivial program using only the probe's own headers fails to
+    /// compile, that failure is returned as an error instead, since
+    /// it indicates a problem with the probe or the toolchain.
+    pub fn has_header(&self, header: &str) -> CProbeResult<bool> {
+        self.compiles_after_control(vec![header], "return 0;")
+    }
+
+    /// Check whether a type is defined as a complete type.
+    ///
+    /// Errors are handled as described for `has_header`. Note that a struct
+    /// that has only been declared, not defined, is treated as missing. The
+    /// type is used as the type in a `typedef`, so expressions (e.g. `errno`)
+    /// are not mistaken for types, but neither are types written with an
+    /// abstract declarator, such as `int[4]` or `int (*)(void)`.
+    pub fn has_type(&self, type_: &str) -> CProbeResult<bool> {
+        self.compiles_after_control(vec![],
+                                    &format!("typedef {} probe_c_api_type;\n\
+                                              (void) \
+                                              sizeof(probe_c_api_type);\n\
+                                              return 0;",
+                                             type_))
+    }
+
+    /// Check whether a struct or union type has a given member. As with
+    /// `offset_of`, the `field` may be a path to a nested member.
+    ///
+    /// Errors are handled as described for `has_header`.
+    pub fn has_member(&self, type_: &str, field: &str) -> CProbeResult<bool> {
+        self.compiles_after_control(vec![],
+                                    &format!("(void) sizeof((({} *) 0)->{});\n\
+                                              return 0;",
+                                             type_, field))
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Probing of floating-point constants.
+
+use super::{CProbeResult, EvaluationStrategy, Probe};
+use super::CProbeError::*;
+
+/// The C type of a floating-point constant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CFloatType {
+    /// `float`
+    Float,
+    /// `double`
+    Double,
+    /// `long double`
+    LongDouble,
+    /// Any other type, e.g. an integer constant.
+    Other,
+}
+
+/// The value of a floating-point constant, along with its C type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FloatConstant<T> {
+    /// The value, converted to the requested Rust type.
+    pub value: T,
+    /// The type of the C expression.
+    pub c_type: CFloatType,
+}
+
+// Expression that selects a code for the floating-point type of `constant`.
+fn type_code_expression(constant: &str) -> String {
+    format!("_Generic(({}), float: 1, double: 2, long double: 3, default: 0)",
+            constant)
+}
+
+fn type_from_code(code: u8) -> CFloatType {
+    match code {
+        1 => CFloatType::Float,
+        2 => CFloatType::Double,
+        3 => CFloatType::LongDouble,
+        _ => CFloatType::Other,
+    }
+}
+
+// Multiply by a power of two without letting intermediate results overflow or
+// underflow, as long as the final result is representable.
+fn ldexp(mut value: f64, mut exponent: i32) -> f64 {
+    while exponent > 1000 {
+        value *= 2f64.powi(1000);
+        exponent -= 1000;
+    }
+    while exponent < -1000 {
+        value *= 2f64.powi(-1000);
+        exponent += 1000;
+    }
+    value * 2f64.powi(exponent)
+}
+
+// Parse a number printed by C's `%a` format (e.g. `-0x1.8p+1`), including
+// the forms used for infinities and NaNs.
+fn parse_hex_float(text: &str) -> Option<f64> {
+    let (negative, rest) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let magnitude = match &rest.to_ascii_lowercase()[..] {
+        "inf" | "infinity" => f64::INFINITY,
+        "nan" => f64::NAN,
+        rest => {
+            let rest = rest.strip_prefix("0x")?;
+            let mut parts = rest.splitn(2, 'p');
+            let digits = parts.next()?;
+            let mut exponent: i32 = parts.next()?.parse().ok()?;
+            let mut parts = digits.splitn(2, '.');
+            let integer_digits = parts.next()?;
+            let fraction_digits = parts.next().unwrap_or("");
+            if integer_digits.is_empty() && fraction_digits.is_empty() {
+                return None;
+            }
+            let mut mantissa: u64 = 0;
+            for digit in integer_digits.chars() {
+                let digit = u64::from(digit.to_digit(16)?);
+                // Past the precision of an `f64`, extra digits only scale the
+                // value.
+                if mantissa >> 56 == 0 {
+                    mantissa = mantissa * 16 + digit;
+                } else {
+                    exponent += 4;
+                }
+            }
+            for digit in fraction_digits.chars() {
+                let digit = u64::from(digit.to_digit(16)?);
+                if mantissa >> 56 == 0 {
+                    mantissa = mantissa * 16 + digit;
+                    exponent -= 4;
+                }
+            }
+            ldexp(mantissa as f64, exponent)
+        }
+    };
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+impl<'a> Probe<'a> {
+    // Get a floating-point constant, converted to `conversion_type` in C,
+    // then to a `double` (which must be exact).
+    fn float_constant_as_f64(&self, constant: &str, conversion_type: &str)
+                             -> CProbeResult<FloatConstant<f64>> {
+        let converted = format!("(double) ({}) ({})",
+                                conversion_type, constant);
+        if self.strategy != EvaluationStrategy::CompileAndRun {
+            return self.float_constant_from_object_file(constant, &converted);
+        }
+        let source = self.main_source_template(
+            vec!["<stdio.h>"],
+            &format!("printf(\"%d\\n\", {});\n\
+                      printf(\"%a\\n\", {});\n\
+                      return 0;",
+                     type_code_expression(constant),
+                     converted),
+        );
+        let compile_run_output = self.check_run(&source)?;
+        let run_out_string =
+            compile_run_output.successful_run_output_for(&source)?;
+        let mut lines = run_out_string.lines();
+        let c_type = type_from_code(lines.next().and_then(|line| {
+            line.trim().parse().ok()
+        }).unwrap_or(0));
+        match lines.next().and_then(|line| parse_hex_float(line.trim())) {
+            Some(value) => Ok(FloatConstant { value, c_type }),
+            None => Err(OtherError("unexpected output from probe program"
+                                   .to_string())),
+        }
+    }
+
+    // Get a floating-point constant without running anything, by reading the
+    // representation of the converted value, and the type code, from a
+    // single compiled file.
+    fn float_constant_from_object_file(&self, constant: &str, converted: &str)
+                                       -> CProbeResult<FloatConstant<f64>> {
+        let source = format!("{}\n\
+                              double probe_c_api_value = {};\n\
+                              unsigned char probe_c_api_type_code = {};\n\
+                              int main(void) {{ return 0; }}\n",
+                             self.header_includes(&[]),
+                             converted,
+                             type_code_expression(constant));
+        self.compile_to_elf(&source, |elf| {
+            let bytes = elf.symbol_contents("probe_c_api_value")
+                           .map_err(OtherError)?;
+            let code = elf.symbol_contents("probe_c_api_type_code")
+                          .map_err(OtherError)?;
+            if bytes.len() != 8 || code.len() != 1 {
+                return Err(OtherError("unexpected size of compiled constant"
+                                      .to_string()));
+            }
+            Ok(FloatConstant {
+                value: f64::from_bits(elf.endianness.decode(&bytes)),
+                c_type: type_from_code(code[0]),
+            })
+        })?
+    }
+
+    /// Get the value of a floating-point constant, converted to a C `double`.
+    ///
+    /// The value is printed in C99 hexadecimal floating-point format (`%a`),
+    /// so no precision is lost in transferring it from C to Rust. Infinities
+    /// and NaNs are also handled. The result includes the type of the C
+    /// expression, as found using C11's `_Generic`. A `long double` constant
+    /// is rounded to `double`, since Rust has no equivalent type.
+    ///
+    /// If the probe is not using `EvaluationStrategy::CompileAndRun`, the
+    /// value is instead read from a compiled file, as described for
+    /// `object_representation`, and the target is assumed to use the same
+    /// floating-point format as the host. This applies to
+    /// `EvaluationStrategy::CompileOnly` too, so the compiler must then
+    /// produce ELF files, or an `OtherError` is returned.
+    pub fn double_constant(&self, constant: &str)
+                           -> CProbeResult<FloatConstant<f64>> {
+        self.float_constant_as_f64(constant, "double")
+    }
+
+    /// Get the value of a floating-point constant, converted to a C `float`.
+    ///
+    /// See the documentation for `double_constant` for details.
+    pub fn float_constant(&self, constant: &str)
+                          -> CProbeResult<FloatConstant<f32>> {
+        let constant = self.float_constant_as_f64(constant, "float")?;
+        Ok(FloatConstant {
+            value: constant.value as f32,
+            c_type: constant.c_type,
+        })
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks for the existence of functions.
+
+use super::{CProbeResult, EvaluationStrategy, Probe};
+
+/// Result of checking for a function with `Probe::has_function`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FunctionCheck {
+    /// Whether the function is declared by the probe's headers.
+    pub declared: bool,
+    /// Whether a reference to the function can be resolved at link time.
+    pub links: bool,
+}
+
+impl<'a> Probe<'a> {
+    /// Check whether a function is declared in the probe's headers, and
+    /// whether it can be linked, similar to autoconf's `AC_CHECK_FUNC`.
+    ///
+    /// The function is considered to be declared if taking its address
+    /// compiles. It is considered to link if a program that uses its address
+    /// can be built and run successfully. If the function is not declared,
+    /// the link check supplies its own (deliberately vague) declaration, so
+    /// that functions missing from the headers can still be found in the
+    /// library.
+    ///
+    /// If the probe is not using `EvaluationStrategy::CompileAndRun`, nothing
+    /// is run, and a function is considered to link if the program builds.
+    /// Note that in that case, whether or not a reference to a missing
+    /// function causes an error depends on the `compile_to` command actually
+    /// linking the program.
+    pub fn has_function(&self, name: &str) -> CProbeResult<FunctionCheck> {
+        // Using the address inside `sizeof` doesn't create a reference to the
+        // symbol, so this only checks the declaration.
+        let declared_source = self.main_source_template(
+            vec![],
+            &format!("(void) sizeof(&{});\nreturn 0;", name),
+        );
+        let declared = self.check_compile(&declared_source)?.status.success();
+        let links_source = if declared {
+            self.main_source_template(
+                vec![],
+                &format!("void (*volatile probe_c_api_function)(void) =\n\
+                          (void (*)(void)) &{};\n\
+                          return probe_c_api_function == 0;",
+                         name),
+            )
+        } else {
+            format!("char {0}(void);\n\
+                     int main(void) {{\n\
+                     char (*volatile probe_c_api_function)(void) = &{0};\n\
+                     return probe_c_api_function == 0;\n\
+                     }}\n",
+                    name)
+        };
+        let links = if self.strategy == EvaluationStrategy::CompileAndRun {
+            match self.check_run(&links_source)?.run_output {
+                Some(run_output) => run_output.status.success(),
+                None => false,
+            }
+        } else {
+            self.check_compile(&links_source)?.status.success()
+        };
+        Ok(FunctionCheck {
+            declared,
+            links,
+        })
+    }
+}
@@ -0,0 +1,319 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A client for the jobserver that `make` and Cargo use to limit the number of
+//! jobs running at once.
+//!
+//! Each job needs a token. Every process starts with one implicit token, and
+//! takes further tokens by reading a byte from the jobserver, which it writes
+//! back when the job is done.
+
+use std::env;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+#[cfg(unix)]
+use std::fs::{self, File, OpenOptions};
+#[cfg(unix)]
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::mem::ManuallyDrop;
+
+/// A connection to a `make` or Cargo jobserver.
+#[derive(Clone, Debug)]
+pub struct Jobserver {
+    #[cfg(unix)]
+    pipe: Arc<Pipe>,
+}
+
+// The two ends of a jobserver's pipe. Descriptors inherited from the parent
+// belong to the whole process, so they are never closed; a FIFO opened by
+// `connect` is closed when the last `Jobserver` using it is dropped.
+#[cfg(unix)]
+#[derive(Debug)]
+struct Pipe {
+    read: ManuallyDrop<File>,
+    write: ManuallyDrop<File>,
+    owned: bool,
+}
+
+#[cfg(unix)]
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe {
+                ManuallyDrop::drop(&mut self.read);
+                ManuallyDrop::drop(&mut self.write);
+            }
+        }
+    }
+}
+
+// The jobserver found by `Jobserver::from_env`, once it has been looked for.
+static FROM_ENV: Mutex<Option<Option<Jobserver>>> = Mutex::new(None);
+
+// Find the jobserver argument among `make` flags. If there are several, the
+// last one wins.
+fn jobserver_auth(flags: &str) -> Option<&str> {
+    flags.split_whitespace().rev().find_map(|flag| {
+        flag.strip_prefix("--jobserver-auth=")
+            .or_else(|| flag.strip_prefix("--jobserver-fds="))
+    })
+}
+
+impl Jobserver {
+    /// Find the jobserver passed down by Cargo or `make`, through the
+    /// `CARGO_MAKEFLAGS`, `MAKEFLAGS` or `MFLAGS` environment variables.
+    ///
+    /// The environment is only checked, and the jobserver only opened, the
+    /// first time this is called; later calls return the same connection.
+    pub fn from_env() -> Option<Jobserver> {
+        let mut from_env = FROM_ENV.lock().unwrap();
+        from_env.get_or_insert_with(|| {
+            ["CARGO_MAKEFLAGS", "MAKEFLAGS", "MFLAGS"].iter()
+                .filter_map(|name| env::var(name).ok())
+                .filter_map(|flags| Jobserver::from_makeflags(&flags))
+                .next()
+        }).clone()
+    }
+
+    /// Connect to the jobserver described by a set of `make` flags, which may
+    /// name either a FIFO (`--jobserver-auth=fifo:PATH`) or a pair of
+    /// inherited pipe file descriptors (`--jobserver-auth=R,W`).
+    ///
+    /// `None` is returned if there is no jobserver, or it can't be used. Only
+    /// Unix jobservers are currently supported.
+    pub fn from_makeflags(flags: &str) -> Option<Jobserver> {
+        Jobserver::connect(jobserver_auth(flags)?)
+    }
+
+    #[cfg(unix)]
+    fn connect(auth: &str) -> Option<Jobserver> {
+        use std::os::unix::fs::FileTypeExt;
+        use std::os::unix::io::FromRawFd;
+        if let Some(path) = auth.strip_prefix("fifo:") {
+            let fifo = OpenOptions::new().read(true).write(true).open(path)
+                                         .ok()?;
+            let write = fifo.try_clone().ok()?;
+            return Some(Jobserver {
+                pipe: Arc::new(Pipe {
+                    read: ManuallyDrop::new(fifo),
+                    write: ManuallyDrop::new(write),
+                    owned: true,
+                }),
+            });
+        }
+        let mut fds = auth.splitn(2, ',');
+        let read_fd: i32 = fds.next()?.parse().ok()?;
+        let write_fd: i32 = fds.next()?.parse().ok()?;
+        // The descriptors may not have been inherited, or may since have been
+        // reused for something else, so make sure that they are pipes.
+        for fd in &[read_fd, write_fd] {
+            if *fd < 0 {
+                return None;
+            }
+            let metadata = fs::metadata(format!("/dev/fd/{}", fd)).ok()?;
+            if !metadata.file_type().is_fifo() {
+                return None;
+            }
+        }
+        unsafe {
+            Some(Jobserver {
+                pipe: Arc::new(Pipe {
+                    read: ManuallyDrop::new(File::from_raw_fd(read_fd)),
+                    write: ManuallyDrop::new(File::from_raw_fd(write_fd)),
+                    owned: false,
+                }),
+            })
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn connect(_auth: &str) -> Option<Jobserver> {
+        None
+    }
+
+    #[cfg(unix)]
+    fn acquire(&self) -> io::Result<u8> {
+        let mut token = [0];
+        (&*self.pipe.read).read_exact(&mut token)?;
+        Ok(token[0])
+    }
+
+    #[cfg(not(unix))]
+    fn acquire(&self) -> io::Result<u8> {
+        Err(io::Error::new(io::ErrorKind::Other,
+                           "jobservers are not supported"))
+    }
+
+    #[cfg(unix)]
+    fn release(&self, token: u8) -> io::Result<()> {
+        (&*self.pipe.write).write_all(&[token])
+    }
+
+    #[cfg(not(unix))]
+    fn release(&self, _token: u8) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// A token held by a worker.
+enum Token {
+    // The token that the process holds implicitly.
+    Implicit,
+    // A token read from the jobserver.
+    Acquired(u8),
+}
+
+struct PoolState {
+    implicit_free: bool,
+    // Tokens that have been read, but not yet handed to a worker.
+    acquired: Vec<u8>,
+    waiting: usize,
+    finished: bool,
+    // Whether the helper thread is reading from the jobserver, and whether
+    // the pool has written a byte to wake it up.
+    reading: bool,
+    woken: bool,
+    // Whether the jobserver has stopped working.
+    broken: bool,
+}
+
+// Hands out tokens to the workers of a single `run_parallel` call.
+//
+// Reads from the jobserver can't be cancelled, so they are done by a helper
+// thread. If the helper is still reading when the pool is dropped, the pool
+// writes an extra byte to the jobserver to wake it up, and the helper keeps
+// whatever byte it reads in place of that one, so the number of tokens is
+// unchanged. Any other token that the helper reads after the pool is
+// finished is written straight back.
+pub(crate) struct TokenPool {
+    jobserver: Jobserver,
+    shared: Arc<(Mutex<PoolState>, Condvar)>,
+}
+
+impl TokenPool {
+    pub(crate) fn new(jobserver: &Jobserver) -> TokenPool {
+        let shared = Arc::new((Mutex::new(PoolState {
+            implicit_free: true,
+            acquired: Vec::new(),
+            waiting: 0,
+            finished: false,
+            reading: false,
+            woken: false,
+            broken: false,
+        }), Condvar::new()));
+        let helper_jobserver = jobserver.clone();
+        let helper_shared = shared.clone();
+        thread::spawn(move || {
+            let (ref mutex, ref condvar) = *helper_shared;
+            loop {
+                {
+                    let mut state = mutex.lock().unwrap();
+                    while !state.finished
+                          && state.waiting <= state.acquired.len() {
+                        state = condvar.wait(state).unwrap();
+                    }
+                    if state.finished {
+                        return;
+                    }
+                    state.reading = true;
+                }
+                let token = helper_jobserver.acquire();
+                let mut state = mutex.lock().unwrap();
+                state.reading = false;
+                match token {
+                    Ok(..) if state.woken => {}
+                    Ok(token) => {
+                        if state.finished
+                           || state.waiting <= state.acquired.len() {
+                            let _ = helper_jobserver.release(token);
+                        } else {
+                            state.acquired.push(token);
+                        }
+                    }
+                    Err(..) => { state.broken = true; }
+                }
+                condvar.notify_all();
+                if state.finished || state.broken {
+                    return;
+                }
+            }
+        });
+        TokenPool {
+            jobserver: jobserver.clone(),
+            shared,
+        }
+    }
+
+    // Wait for a token. If the jobserver has stopped working, only the
+    // implicit token is handed out, so jobs run one at a time.
+    fn acquire(&self) -> Token {
+        let (ref mutex, ref condvar) = *self.shared;
+        let mut state = mutex.lock().unwrap();
+        state.waiting += 1;
+        condvar.notify_all();
+        let token = loop {
+            if state.implicit_free {
+                state.implicit_free = false;
+                break Token::Implicit;
+            }
+            if let Some(token) = state.acquired.pop() {
+                break Token::Acquired(token);
+            }
+            state = condvar.wait(state).unwrap();
+        };
+        state.waiting -= 1;
+        token
+    }
+
+    // Run a job while holding a token.
+    pub(crate) fn with_token<T, F>(&self, job: F) -> T
+        where F: FnOnce() -> T {
+        let token = self.acquire();
+        let result = job();
+        self.release(token);
+        result
+    }
+
+    // Give back a token after a job is done.
+    fn release(&self, token: Token) {
+        let (ref mutex, ref condvar) = *self.shared;
+        match token {
+            Token::Implicit => {
+                mutex.lock().unwrap().implicit_free = true;
+                condvar.notify_all();
+            }
+            Token::Acquired(token) => {
+                let _ = self.jobserver.release(token);
+            }
+        }
+    }
+}
+
+impl Drop for TokenPool {
+    fn drop(&mut self) {
+        let (ref mutex, ref condvar) = *self.shared;
+        let mut state = mutex.lock().unwrap();
+        state.finished = true;
+        for token in state.acquired.drain(..) {
+            let _ = self.jobserver.release(token);
+        }
+        if state.reading && !state.broken {
+            state.woken = self.jobserver.release(b'+').is_ok();
+        }
+        condvar.notify_all();
+    }
+}
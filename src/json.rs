@@ -0,0 +1,307 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Just enough JSON to write and read back probe reports.
+
+use std::fmt::Write as FormatWrite;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// A JSON value. Numbers are kept as text, so that integers of any size
+// survive a round trip, and objects keep their keys in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+fn write_string(out: &mut String, string: &str) {
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(out, "\\u{:04x}", c as u32).unwrap();
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+impl Json {
+    // Look up a key in an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref members) => {
+                members.iter().find(|member| member.0 == key)
+                              .map(|member| &member.1)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Json::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match *self {
+            Json::Array(ref values) => Some(values),
+            _ => None,
+        }
+    }
+
+    // Parse a number, e.g. as an `i64` or `u8`.
+    pub(crate) fn as_number<T: ::std::str::FromStr>(&self) -> Option<T> {
+        match *self {
+            Json::Number(ref number) => number.parse().ok(),
+            _ => None,
+        }
+    }
+
+    // Write the value, with each array element and object member on its own
+    // line, so that the output diffs well. Arrays of numbers are kept on one
+    // line.
+    pub(crate) fn write(&self, out: &mut String, indent: usize) {
+        let newline = |out: &mut String, indent: usize| {
+            out.push('\n');
+            for _ in 0..indent {
+                out.push_str("  ");
+            }
+        };
+        match *self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(value) => write!(out, "{}", value).unwrap(),
+            Json::Number(ref number) => out.push_str(number),
+            Json::String(ref string) => write_string(out, string),
+            Json::Array(ref values) => {
+                let compact = values.iter().all(|value| {
+                    matches!(*value, Json::Number(..))
+                });
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                        if compact {
+                            out.push(' ');
+                        }
+                    }
+                    if !compact {
+                        newline(out, indent + 1);
+                    }
+                    value.write(out, indent + 1);
+                }
+                if !compact && !values.is_empty() {
+                    newline(out, indent);
+                }
+                out.push(']');
+            }
+            Json::Object(ref members) => {
+                out.push('{');
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 1);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                }
+                if !members.is_empty() {
+                    newline(out, indent);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    // Parse a complete JSON document.
+    pub(crate) fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+            line: 1,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.peek() {
+            None => Ok(value),
+            Some(..) => parser.error("unexpected text after JSON value"),
+        }
+    }
+}
+
+struct Parser<'t> {
+    chars: Peekable<Chars<'t>>,
+    line: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("line {}: {}", self.line, message))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '\n' => { self.line += 1; }
+                ' ' | '\t' | '\r' => {}
+                _ => { return; }
+            }
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => self.error(&format!("expected `{}`", expected)),
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || "+-.".contains(c)) {
+                break;
+            }
+            word.push(c);
+            self.chars.next();
+        }
+        word
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => { return Ok(string); }
+                Some('\\') => {
+                    let escaped = match self.chars.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let digits: String =
+                                self.chars.by_ref().take(4).collect();
+                            // Surrogate pairs are never written by `write`,
+                            // so they are not supported.
+                            match u32::from_str_radix(&digits, 16).ok()
+                                      .and_then(::std::char::from_u32) {
+                                Some(c) => c,
+                                None => {
+                                    return self.error("bad unicode escape");
+                                }
+                            }
+                        }
+                        _ => { return self.error("bad escape in string"); }
+                    };
+                    string.push(escaped);
+                }
+                Some(c) => {
+                    if c == '\n' {
+                        self.line += 1;
+                    }
+                    string.push(c);
+                }
+                None => { return self.error("unterminated string"); }
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(&'"') => self.string().map(Json::String),
+            Some(&'[') => {
+                self.chars.next();
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.chars.peek() == Some(&']') {
+                    self.chars.next();
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => {}
+                        Some(']') => { return Ok(Json::Array(values)); }
+                        _ => { return self.error("expected `,` or `]`"); }
+                    }
+                }
+            }
+            Some(&'{') => {
+                self.chars.next();
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.chars.peek() == Some(&'}') {
+                    self.chars.next();
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    members.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => {}
+                        Some('}') => { return Ok(Json::Object(members)); }
+                        _ => { return self.error("expected `,` or `}`"); }
+                    }
+                }
+            }
+            _ => {
+                let word = self.word();
+                match &word[..] {
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    _ if word.starts_with(|c: char| {
+                        c == '-' || c.is_ascii_digit()
+                    }) && word.parse::<f64>().is_ok() => {
+                        Ok(Json::Number(word))
+                    }
+                    _ => self.error("expected a JSON value"),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,278 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Probing of the layout of struct and union fields.
+
+use std::fmt::Write as FormatWrite;
+
+use super::{rust_integer_name, CProbeResult, Probe};
+use super::CProbeError::*;
+use query::{Query, QueryValue};
+
+// Words that can't be used as plain Rust identifiers.
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const",
+    "continue", "do", "dyn", "else", "enum", "extern", "false", "final", "fn",
+    "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move",
+    "mut", "override", "priv", "pub", "ref", "return", "static", "struct",
+    "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+// Keywords that can't be used even as raw identifiers.
+const RUST_PATH_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+// Turn a C name, or a path to a nested field, into a Rust identifier. Each
+// character that can't appear in an identifier is replaced by `_`, so that
+// distinct C names only collide if they differ in those characters.
+pub(crate) fn rust_identifier(name: &str) -> String {
+    let identifier: String = name.chars().map(|c| {
+        if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }
+    }).collect();
+    if RUST_KEYWORDS.contains(&&identifier[..]) {
+        format!("r#{}", identifier)
+    } else if RUST_PATH_KEYWORDS.contains(&&identifier[..]) {
+        format!("{}_", identifier)
+    } else {
+        identifier
+    }
+}
+
+// The queries answered by `struct_layout`: the size and alignment of the
+// type, then the offset, size and alignment of each field.
+fn layout_queries(type_: &str, fields: &[&str]) -> Vec<Query> {
+    let mut queries = vec![Query::SizeOf(type_.to_string()),
+                           Query::AlignOf(type_.to_string())];
+    for field in fields {
+        queries.push(Query::OffsetOf(type_.to_string(), field.to_string()));
+        queries.push(Query::FieldSizeOf(type_.to_string(), field.to_string()));
+        queries.push(Query::FieldAlignOf(type_.to_string(),
+                                         field.to_string()));
+    }
+    queries
+}
+
+// Assemble a layout from the results of `layout_queries`, returning the
+// first error if any part of it could not be probed.
+fn layout_from_results(fields: &[&str],
+                       results: Vec<CProbeResult<QueryValue>>)
+                       -> CProbeResult<StructLayout> {
+    let mut values = Vec::with_capacity(results.len());
+    for result in results {
+        values.push(result.map(|value| {
+            value.as_u64().expect("expected an unsigned query")
+        })?);
+    }
+    let field_layouts: Vec<FieldLayout> =
+        fields.iter().zip(values[2..].chunks(3)).map(|(field, values)| {
+            FieldLayout {
+                name: field.to_string(),
+                offset: values[0],
+                size: values[1],
+                alignment: values[2],
+            }
+        }).collect();
+    let end_of_fields = field_layouts.iter()
+                                     .map(|field| field.offset + field.size)
+                                     .max()
+                                     .unwrap_or(0);
+    Ok(StructLayout {
+        size: values[0],
+        alignment: values[1],
+        fields: field_layouts,
+        trailing_padding: values[0].saturating_sub(end_of_fields),
+    })
+}
+
+// The Rust type of a field of the given size, from the results of the
+// `IsFloating` and `IsSigned` queries for its type. Fields that aren't of an
+// arithmetic type fail to compile for `IsFloating`, and have no Rust type.
+fn field_rust_type(size: u64, is_floating: CProbeResult<QueryValue>,
+                   is_signed: CProbeResult<QueryValue>)
+                   -> CProbeResult<Option<String>> {
+    let is_floating = match is_floating {
+        Ok(value) => value.as_bool().expect("expected a boolean query"),
+        Err(CompileError(..)) => { return Ok(None); }
+        Err(error) => { return Err(error); }
+    };
+    if is_floating {
+        return Ok(match size {
+            4 | 8 => Some(format!("f{}", 8 * size)),
+            _ => None,
+        });
+    }
+    let signed = is_signed?.as_bool().expect("expected a boolean query");
+    Ok(rust_integer_name(signed, size))
+}
+
+/// Layout of a single field within a struct or union.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldLayout {
+    /// The field name (or path, e.g. `a.b.c`) as given by the user.
+    pub name: String,
+    /// Offset of the field from the start of the enclosing type, in bytes.
+    pub offset: u64,
+    /// Size of the field, in bytes.
+    pub size: u64,
+    /// Alignment of the field, in bytes.
+    pub alignment: u64,
+}
+
+/// Layout of a struct or union type, as far as the requested fields describe
+/// it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructLayout {
+    /// Size of the whole type, in bytes.
+    pub size: u64,
+    /// Alignment of the whole type, in bytes.
+    pub alignment: u64,
+    /// Layouts of the requested fields, in the order requested.
+    pub fields: Vec<FieldLayout>,
+    /// Number of bytes after the end of the last of the requested fields.
+    /// This includes both padding and any fields that were not requested.
+    pub trailing_padding: u64,
+}
+
+impl<'a> Probe<'a> {
+    /// Get the offset of a field within a struct or union type, in bytes.
+    ///
+    /// The `field` may be a path to a nested field, e.g. `a.b.c`, or an array
+    /// element, e.g. `a[2]`.
+    pub fn offset_of(&self, type_: &str, field: &str) -> CProbeResult<u64> {
+        self.evaluate_u64(Query::OffsetOf(type_.to_string(),
+                                          field.to_string()))
+    }
+
+    /// Get the size of a field within a struct or union type, in bytes.
+    pub fn field_size_of(&self, type_: &str, field: &str)
+                         -> CProbeResult<u64> {
+        self.evaluate_u64(Query::FieldSizeOf(type_.to_string(),
+                                             field.to_string()))
+    }
+
+    /// Get the alignment of a field within a struct or union type, in bytes.
+    ///
+    /// Note that C11 only defines `alignof` for type names, so this method
+    /// depends on the common compiler extension (supported by GCC and Clang)
+    /// that allows it to be applied to an expression.
+    pub fn field_align_of(&self, type_: &str, field: &str)
+                          -> CProbeResult<u64> {
+        self.evaluate_u64(Query::FieldAlignOf(type_.to_string(),
+                                              field.to_string()))
+    }
+
+    /// Get the offset, size and alignment of several fields at once, along
+    /// with the size and alignment of the enclosing type.
+    ///
+    /// Everything is answered with a single batch (see `evaluate_batch`). If
+    /// any part of the layout cannot be probed, the first error encountered
+    /// is returned.
+    pub fn struct_layout(&self, type_: &str, fields: &[&str])
+                         -> CProbeResult<StructLayout> {
+        let results = self.evaluate_batch(&layout_queries(type_, fields))?;
+        layout_from_results(fields, results)
+    }
+
+    /// Generate a `#[repr(C)]` Rust struct definition with the same layout
+    /// as a C struct type.
+    ///
+    /// The struct is given the name `rust_name`, and a member for each of the
+    /// `fields`, named after the C field (with any `.` in a nested path
+    /// replaced by `_`). Each member's type is found in the same way as by
+    /// `equivalent_rust_integer` or `equivalent_rust_float`; fields of any
+    /// other kind, or with no equivalent Rust type, become byte arrays. Gaps
+    /// between fields, including any undocumented fields, become explicit
+    /// `[u8; N]` padding members, as do any bytes after the last field. If
+    /// the C type is more strictly aligned than its members would require,
+    /// an `align` representation hint is added, so that the generated type
+    /// has the same size and alignment as the C type.
+    ///
+    /// This assumes that each Rust primitive type has the same alignment as
+    /// the C type that it replaces. Finding the type of a field relies on the
+    /// `__typeof__` extension supported by GCC and Clang. An `OtherError` is
+    /// returned if any of the fields overlap (as in a union), or if the C type
+    /// is less strictly aligned than its fields (as in a packed struct).
+    ///
+    /// The layout and the kinds of all the fields are probed with a single
+    /// batch (see `evaluate_batch`).
+    pub fn rust_struct_definition(&self, type_: &str, rust_name: &str,
+                                  fields: &[&str]) -> CProbeResult<String> {
+        let mut queries = layout_queries(type_, fields);
+        let layout_len = queries.len();
+        for field in fields {
+            let field_type = format!("__typeof__((({} *) 0)->{})",
+                                     type_, field);
+            queries.push(Query::IsFloating(field_type.clone()));
+            queries.push(Query::IsSigned(field_type));
+        }
+        let mut results = self.evaluate_batch(&queries)?;
+        let kind_results = results.split_off(layout_len);
+        let layout = layout_from_results(fields, results)?;
+        let mut members = Vec::with_capacity(fields.len());
+        let mut kind_results = kind_results.into_iter();
+        for field in &layout.fields {
+            let is_floating = kind_results.next().unwrap();
+            let is_signed = kind_results.next().unwrap();
+            members.push((field,
+                          field_rust_type(field.size, is_floating,
+                                          is_signed)?));
+        }
+        members.sort_by_key(|member| member.0.offset);
+        let mut body = String::new();
+        let mut end = 0;
+        let mut padding_count = 0;
+        let mut max_alignment = 1;
+        {
+            let mut add_padding = |body: &mut String, bytes: u64| {
+                writeln!(body, "    pub _padding{}: [u8; {}],",
+                         padding_count, bytes).unwrap();
+                padding_count += 1;
+            };
+            for (field, rust_type) in members {
+                if field.offset < end {
+                    return Err(OtherError(format!("field {} of {} overlaps \
+                                                   another field",
+                                                  field.name, type_)));
+                }
+                if field.offset > end {
+                    add_padding(&mut body, field.offset - end);
+                }
+                let rust_type = match rust_type {
+                    Some(rust_type) => {
+                        max_alignment = max_alignment.max(field.alignment);
+                        rust_type
+                    }
+                    None => format!("[u8; {}]", field.size),
+                };
+                writeln!(&mut body, "    pub {}: {},",
+                         rust_identifier(&field.name), rust_type).unwrap();
+                end = field.offset + field.size;
+            }
+            if layout.size > end {
+                add_padding(&mut body, layout.size - end);
+            }
+        }
+        if layout.alignment < max_alignment {
+            return Err(OtherError(format!("{} is less strictly aligned than \
+                                           its fields",
+                                          type_)));
+        }
+        let repr = if layout.alignment > max_alignment {
+            format!("#[repr(C, align({}))]", layout.alignment)
+        } else {
+            "#[repr(C)]".to_string()
+        };
+        Ok(format!("{}\npub struct {} {{\n{}}}\n", repr, rust_name, body))
+    }
+}
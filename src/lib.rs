@@ -44,6 +44,33 @@
 
 extern crate rand;
 
+mod bindings;
+mod builder;
+mod cache;
+mod compile_only;
+mod compiler;
+mod diagnostics;
+mod elf;
+mod enums;
+mod existence;
+mod floats;
+mod functions;
+mod jobserver;
+mod json;
+mod layout;
+mod macros;
+#[cfg(any(unix, windows))]
+mod mock;
+mod object_file;
+mod parallel;
+mod pkg_config;
+mod preprocess;
+mod query;
+mod report;
+mod spec;
+mod strings;
+mod wide_integers;
+
 use std::boxed::Box;
 use std::default::Default;
 use std::env;
@@ -55,10 +82,35 @@ use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::{self, Command};
 use std::str::FromStr;
+use std::sync::Arc;
 
 use rand::random;
 
+pub use bindings::{BindingDiagnostic, BindingItem, Bindings, BindingsWriter,
+                   ItemValue};
+pub use builder::ProbeBuilder;
+pub use compiler::{Compiler, CompilerOptions, GccCompatible, OutputKind};
+pub use diagnostics::{parse_diagnostics, CompileErrorKind, CompileFailure,
+                      CompilerDiagnostic, Severity};
+pub use enums::EnumInfo;
+pub use floats::{CFloatType, FloatConstant};
+pub use functions::FunctionCheck;
+pub use jobserver::Jobserver;
+pub use layout::{FieldLayout, StructLayout};
+pub use macros::{MacroExpansion, MacroKind};
+#[cfg(any(unix, windows))]
+pub use mock::{MockBackend, MockOutcome};
+pub use object_file::{Endianness, TargetInfo};
+pub use pkg_config::{PkgConfig, PkgConfigError, PkgConfigLibrary};
+pub use preprocess::PreprocessMode;
+pub use query::{Query, QueryValue};
+pub use report::{ProbeReport, QueryEntry, ReportEntry, ReportError,
+                 ToolchainSettings};
+pub use spec::{ApiSpec, SpecError};
+
 use NewProbeError::*;
+use cache::{Cache, CachedOutputs};
+use diagnostics::compile_error;
 use CProbeError::*;
 use CTypeKind::*;
 
@@ -125,7 +177,7 @@ impl Error for NewProbeError {
                                          directory",
         }
     }
-    fn cause(&self) -> Option<&Error> {
+    fn cause(&self) -> Option<&dyn Error> {
         match *self {
             WorkDirMetadataInaccessible(ref error) => Some(error),
             WorkDirNotADirectory(..) => None,
@@ -159,7 +211,7 @@ impl fmt::Debug for CompileRunOutput {
                          output_as_string(&self.compile_output),
                          self.run_output.as_ref().map_or(
                              "None".to_string(),
-                             |output| output_as_string(output)))
+                             output_as_string))
         )
     }
 }
@@ -170,7 +222,16 @@ impl CompileRunOutput {
     /// This function does not panic. If the compilation or run failed, this is
     /// reported in the error. If the program's output is not valid UTF-8, lossy
     /// conversion is performed.
+    ///
+    /// The program's source is not known here, so compiler diagnostics in a
+    /// `CompileError` are not matched to source lines.
     pub fn successful_run_output(&self) -> CProbeResult<String> {
+        self.successful_run_output_for("")
+    }
+
+    // As `successful_run_output`, for a program with the given source.
+    pub(crate) fn successful_run_output_for(&self, source: &str)
+                                            -> CProbeResult<String> {
         match self.run_output {
             Some(ref run_output) => {
                 if run_output.status.success() {
@@ -181,22 +242,26 @@ impl CompileRunOutput {
                 }
             }
             None => {
-                Err(CompileError(self.compile_output.clone()))
+                Err(compile_error(self.compile_output.clone(), source))
             }
         }
     }
 }
 
-// FIXME! In general there could be a lot more testing of error paths. The
-// simplest way to do this would be to create a `Probe` that spoofs `Output`s
-// that trigger each of these errors.
-
 /// Error type used when a C API probing program fails to compile or run.
 pub enum CProbeError {
     /// An I/O error prevented the operation from continuing.
     IoError(io::Error),
-    /// Compilation failed.
-    CompileError(process::Output),
+    /// Compilation failed. The details include the compiler's diagnostics,
+    /// and a classification of the first error (see `CompileFailure::kind`).
+    ///
+    /// This is a breaking change: the variant used to hold the compiler's
+    /// `process::Output` directly, which is now the `output` field of the
+    /// `CompileFailure`. Failures are classified by a method rather than by
+    /// separate variants, so that matching on `CompileError` still catches
+    /// every failed compilation, and newly recognized kinds of failure don't
+    /// change this enum again.
+    CompileError(Box<CompileFailure>),
     /// The probing program failed when run. The compilation output is included
     /// to assist debugging.
     RunError(process::Output, process::Output),
@@ -212,9 +277,9 @@ impl fmt::Debug for CProbeError {
                     format_args!("IoError{{ {:?} }}", error)
                 )
             }
-            CompileError(ref output) => {
+            CompileError(ref failure) => {
                 f.write_fmt(
-                    format_args!("CompileError{}", output_as_string(output))
+                    format_args!("CompileError{{ {:?} }}", failure)
                 )
             }
             RunError(ref compile_output, ref run_output) => {
@@ -245,10 +310,10 @@ impl fmt::Display for CProbeError {
                     format_args!("I/O error: {}", error)
                 )
             }
-            CompileError(ref output) => {
+            CompileError(ref failure) => {
                 f.write_fmt(
                     format_args!("compilation error with output: {}",
-                                 output_as_string(output))
+                                 output_as_string(&failure.output))
                 )
             }
             RunError(_, ref run_output) => {
@@ -273,7 +338,7 @@ impl Error for CProbeError {
             OtherError(ref string) => string,
         }
     }
-    fn cause(&self) -> Option<&Error> {
+    fn cause(&self) -> Option<&dyn Error> {
         match *self {
             IoError(ref error) => Some(error),
             CompileError(..) | RunError(..) | OtherError(..) => None,
@@ -281,6 +346,16 @@ impl Error for CProbeError {
     }
 }
 
+impl CProbeError {
+    /// If this error is a `CompileError`, the details of the failure.
+    pub fn compile_failure(&self) -> Option<&CompileFailure> {
+        match *self {
+            CompileError(ref failure) => Some(failure),
+            IoError(..) | RunError(..) | OtherError(..) => None,
+        }
+    }
+}
+
 impl From<io::Error> for CProbeError {
     fn from(error: io::Error) -> Self {
         IoError(error)
@@ -303,7 +378,7 @@ pub enum CTypeKind {
 
 /// Information about a C type, based on a combination of user input and
 /// information that can be discovered by probing.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct CType {
     /// The size in bytes.
     pub size: u64,
@@ -311,6 +386,61 @@ pub struct CType {
     pub kind: CTypeKind,
 }
 
+// Shared forms of the closures passed to `Probe::new`.
+// The closures must be `Send + Sync` so that a `Probe` can be shared between
+// threads (see `run_parallel`).
+type CompileCommand<'a> =
+    Arc<dyn Fn(&Path, &Path) -> CommandResult + Send + Sync + 'a>;
+type RunCommand<'a> = Arc<dyn Fn(&Path) -> CommandResult + Send + Sync + 'a>;
+type PreprocessCommand<'a> =
+    Arc<dyn Fn(&Path, PreprocessMode) -> CommandResult + Send + Sync + 'a>;
+type IdentifyCommand<'a> = Arc<dyn Fn() -> String + Send + Sync + 'a>;
+
+/// The method used by a `Probe` to get answers out of the C compiler.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvaluationStrategy {
+    /// Compile and run a program that prints each answer. This is the default.
+    CompileAndRun,
+    /// Only compile programs, never run them. Answers are found by compiling
+    /// static assertions and doing a binary search over the range of possible
+    /// values, in the style of autoconf's `AC_COMPUTE_INT`.
+    ///
+    /// This is useful for cross-compilation, since the `run` command is never
+    /// used, but it takes many compilations per query, and only works when the
+    /// answer is an integer constant expression. For instance, a macro that
+    /// expands to a literal works, but a `const` global variable does not.
+    CompileOnly,
+    /// Compile a program that stores each answer in an initialized global
+    /// variable, then read the answers out of the compiled file, without
+    /// running it.
+    ///
+    /// Like `CompileOnly`, this never uses the `run` command, but only one
+    /// compilation is needed for a whole batch of queries. It requires the
+    /// object file command (see `Probe::set_compile_object`), or else the
+    /// `compile_to` command, to produce an ELF file that has not been
+    /// stripped of its symbol table, and the answers must be usable as static
+    /// initializers.
+    ObjectFile,
+}
+
+impl EvaluationStrategy {
+    // The name used for the strategy in spec files and reports.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            EvaluationStrategy::CompileAndRun => "compile-and-run",
+            EvaluationStrategy::CompileOnly => "compile-only",
+            EvaluationStrategy::ObjectFile => "object-file",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<EvaluationStrategy> {
+        let strategies = [EvaluationStrategy::CompileAndRun,
+                          EvaluationStrategy::CompileOnly,
+                          EvaluationStrategy::ObjectFile];
+        strategies.iter().cloned().find(|strategy| strategy.name() == name)
+    }
+}
+
 /// A struct that stores information about how to compile and run test programs.
 ///
 /// The main functionality of `probe_c_api` is implemented using the methods on
@@ -320,8 +450,13 @@ pub struct CType {
 pub struct Probe<'a> {
     headers: Vec<String>,
     work_dir: PathBuf,
-    compile_to: Box<Fn(&Path, &Path) -> CommandResult + 'a>,
-    run: Box<Fn(&Path) -> CommandResult + 'a>,
+    compile_to: CompileCommand<'a>,
+    run: RunCommand<'a>,
+    compile_object: Option<CompileCommand<'a>>,
+    preprocess: Option<PreprocessCommand<'a>>,
+    identify: Option<IdentifyCommand<'a>>,
+    cache: Option<Cache>,
+    strategy: EvaluationStrategy,
 }
 
 impl<'a> fmt::Debug for Probe<'a> {
@@ -366,14 +501,25 @@ impl<'a> Probe<'a> {
     /// The `run` argument is responsible for running the process and yielding
     /// its status and output, again as a `CommandResult`.
     ///
+    /// Both closures must be `Send + Sync`, so that the `Probe` can be used
+    /// from several threads at once (see `run_parallel`). Closures that only
+    /// build and run a `Command` always are; shared mutable state should use
+    /// types such as `Mutex` or the atomic integers.
+    ///
+    /// Compile errors are classified (see `CompileFailure::kind`) from the
+    /// compiler's English messages, so `compile_to` should run the compiler
+    /// in the C locale, e.g. by setting `LC_ALL=C` on the `Command`, as
+    /// `GccCompatible` does. Otherwise, translated messages are classified
+    /// as `CompileErrorKind::Other`.
+    ///
     /// FIXME! Suggestions for equivalent non-POSIX examples, especially
     /// anything relevant for Windows, are welcomed.
-    pub fn new<C: 'a, R: 'a>(headers: Vec<String>,
+    pub fn new<C, R>(headers: Vec<String>,
                              work_dir: &Path,
                              compile_to: C,
                              run: R) -> Result<Probe<'a>, NewProbeError>
-        where C: Fn(&Path, &Path) -> CommandResult,
-              R: Fn(&Path) -> CommandResult {
+        where C: Fn(&Path, &Path) -> CommandResult + Send + Sync + 'a,
+              R: Fn(&Path) -> CommandResult + Send + Sync + 'a {
         match fs::metadata(work_dir) {
             Ok(metadata) => if !metadata.is_dir() {
                 return Err(WorkDirNotADirectory(work_dir.to_path_buf()));
@@ -381,22 +527,113 @@ impl<'a> Probe<'a> {
             Err(error) => { return Err(WorkDirMetadataInaccessible(error)); }
         }
         Ok(Probe {
-            headers: headers,
+            headers,
             work_dir: work_dir.to_path_buf(),
-            compile_to: Box::new(compile_to),
-            run: Box::new(run),
+            compile_to: Arc::new(compile_to),
+            run: Arc::new(run),
+            compile_object: None,
+            preprocess: None,
+            identify: None,
+            cache: None,
+            strategy: EvaluationStrategy::CompileAndRun,
         })
     }
 
+    /// Construct a `Probe` that uses a `Compiler`, such as `GccCompatible`,
+    /// instead of closures.
+    ///
+    /// The compiler is used to compile programs, to compile object files for
+    /// `EvaluationStrategy::ObjectFile`, and as the preprocess command.
+    /// Programs are run directly, as by `Command::new(exe_path).output()`.
+    /// See `Probe::new` for the meaning of the other arguments.
+    pub fn with_compiler<C>(headers: Vec<String>,
+                            work_dir: &Path,
+                            compiler: C) -> Result<Probe<'a>, NewProbeError>
+        where C: Compiler + Send + Sync + 'a {
+        Probe::with_compiler_and_run(headers, work_dir, compiler, |exe_path| {
+            Command::new(exe_path).output()
+        })
+    }
+
+    // Construct a `Probe` from a `Compiler` and a `run` command.
+    fn with_compiler_and_run<C, R>(headers: Vec<String>,
+                                   work_dir: &Path,
+                                   compiler: C,
+                                   run: R) -> Result<Probe<'a>, NewProbeError>
+        where C: Compiler + Send + Sync + 'a,
+              R: Fn(&Path) -> CommandResult + Send + Sync + 'a {
+        let compiler = Arc::new(compiler);
+        let object_compiler = compiler.clone();
+        let preprocessor = compiler.clone();
+        let identifier = compiler.clone();
+        let mut probe = Probe::new(
+            headers,
+            work_dir,
+            move |source_path, exe_path| {
+                compiler.compile(source_path, exe_path, OutputKind::Executable)
+            },
+            run,
+        )?;
+        probe.set_compile_object(move |source_path, object_path| {
+            object_compiler.compile(source_path, object_path,
+                                    OutputKind::Object)
+        });
+        probe.set_preprocess(move |source_path, mode| {
+            preprocessor.preprocess(source_path, mode)
+        });
+        probe.identify = Some(Arc::new(move || identifier.identity()));
+        Ok(probe)
+    }
+
+    /// Get the strategy used to answer queries.
+    pub fn evaluation_strategy(&self) -> EvaluationStrategy {
+        self.strategy
+    }
+
+    /// Set the strategy used to answer queries. This affects `size_of`,
+    /// `align_of`, `is_signed`, `is_defined_macro`, the integer constant
+    /// methods, and anything else that is built on `evaluate`.
+    pub fn set_evaluation_strategy(&mut self, strategy: EvaluationStrategy) {
+        self.strategy = strategy;
+    }
+
+    /// Set a command that compiles a source file (the first argument) to an
+    /// object file (the second argument), without linking, like `gcc -c $1 -o
+    /// $2`.
+    ///
+    /// If this is set, it is used instead of `compile_to` whenever the output
+    /// is only read, never run, as with `EvaluationStrategy::ObjectFile`.
+    pub fn set_compile_object<C>(&mut self, compile_object: C)
+        where C: Fn(&Path, &Path) -> CommandResult + Send + Sync + 'a {
+        self.compile_object = Some(Arc::new(compile_object));
+    }
+
+    /// Set a command that runs only the preprocessor.
+    ///
+    /// The `preprocess` argument takes a source file `&Path` and yields the
+    /// preprocessor's output on standard output, in a `CommandResult`. For
+    /// `PreprocessMode::Expand` this is roughly equivalent to `gcc -E $1`,
+    /// and for `PreprocessMode::DefinedMacros` to `gcc -dM -E $1`.
+    ///
+    /// A probe has no preprocess command unless one is set here (or the probe
+    /// was created by `Probe::default`). When there is one, `macro_expansion`
+    /// and `is_defined_macro` use it instead of compiling probe programs. The
+    /// cache also needs one (see `enable_cache_in`).
+    pub fn set_preprocess<P>(&mut self, preprocess: P)
+        where P: Fn(&Path, PreprocessMode) -> CommandResult + Send + Sync
+                 + 'a {
+        self.preprocess = Some(Arc::new(preprocess));
+    }
+
     // Create random paths for compilation input/output. This is intended
     // primarily to prevent two concurrently running probes from using each
     // others' files.
     fn random_source_and_exe_paths(&self) -> (PathBuf, PathBuf) {
         let random_suffix = random::<u64>();
-        let source_path = self.work_dir.join(&format!("source-{}.c",
-                                                      random_suffix));
-        let exe_path = self.work_dir.join(&format!("exe-{}",
-                                                   random_suffix))
+        let source_path = self.work_dir.join(format!("source-{}.c",
+                                                     random_suffix));
+        let exe_path = self.work_dir.join(format!("exe-{}",
+                                                  random_suffix))
                                     .with_extension(env::consts::EXE_EXTENSION);
         (source_path, exe_path)
     }
@@ -407,20 +644,26 @@ impl<'a> Probe<'a> {
     /// want to reuse a closure that was used to construct the `Probe`, as well
     /// as for convenience and testing of `probe-c-api` itself.
     pub fn check_compile(&self, source: &str) -> CommandResult {
-        let (source_path, exe_path) = self.random_source_and_exe_paths();
-        try!(write_to_new_file(&source_path, source));
-        let compile_output = try!((*self.compile_to)(&source_path, &exe_path));
-        try!(fs::remove_file(&source_path));
-        // Remove the generated executable if it exists.
-        match fs::remove_file(&exe_path) {
-            Ok(..) => {}
-            Err(error) => {
-                if error.kind() != io::ErrorKind::NotFound {
-                    return Err(error);
+        let mut cached = self.cached("compile", source, || {
+            let (source_path, exe_path) = self.random_source_and_exe_paths();
+            write_to_new_file(&source_path, source)?;
+            let compile_output = (*self.compile_to)(&source_path, &exe_path)?;
+            fs::remove_file(&source_path)?;
+            // Remove the generated executable if it exists.
+            match fs::remove_file(&exe_path) {
+                Ok(..) => {}
+                Err(error) => {
+                    if error.kind() != io::ErrorKind::NotFound {
+                        return Err(error);
+                    }
                 }
             }
-        }
-        Ok(compile_output)
+            Ok(CachedOutputs {
+                outputs: vec![compile_output],
+                contents: None,
+            })
+        })?;
+        Ok(cached.outputs.remove(0))
     }
 
     /// Write a byte slice to a file, then attempt to compile and run it.
@@ -428,50 +671,62 @@ impl<'a> Probe<'a> {
     /// Like `check_compile`, this provides little value, but is available as a
     /// minor convenience.
     pub fn check_run(&self, source: &str) -> io::Result<CompileRunOutput> {
-        let (source_path, exe_path) = self.random_source_and_exe_paths();
-        try!(write_to_new_file(&source_path, source));
-        let compile_output = try!((*self.compile_to)(&source_path, &exe_path));
-        try!(fs::remove_file(&source_path));
-        let run_output;
-        if compile_output.status.success() {
-            run_output = Some(try!((*self.run)(&exe_path)));
-            try!(fs::remove_file(&exe_path));
-        } else {
-            run_output = None;
-        }
+        let cached = self.cached("run", source, || {
+            let (source_path, exe_path) = self.random_source_and_exe_paths();
+            write_to_new_file(&source_path, source)?;
+            let compile_output = (*self.compile_to)(&source_path, &exe_path)?;
+            fs::remove_file(&source_path)?;
+            let mut outputs = vec![compile_output];
+            if outputs[0].status.success() {
+                outputs.push((*self.run)(&exe_path)?);
+                fs::remove_file(&exe_path)?;
+            }
+            Ok(CachedOutputs {
+                outputs,
+                contents: None,
+            })
+        })?;
+        let mut outputs = cached.outputs.into_iter();
         Ok(CompileRunOutput{
-            compile_output: compile_output,
-            run_output: run_output,
+            compile_output: outputs.next().expect("missing compile output"),
+            run_output: outputs.next(),
         })
     }
 
     /// Utility for various checks that use some simple code in `main`.
     fn main_source_template(&self, headers: Vec<&str>, main_body: &str)
                             -> String {
-        let mut header_includes = String::new();
-        for header in &self.headers {
-            write!(&mut header_includes, "#include {}\n", header).unwrap();
-        }
-        for header in &headers {
-            write!(&mut header_includes, "#include {}\n", header).unwrap();
-        }
         format!("{}\n\
                  int main(int argc, char **argv) {{\n\
                  {}\n\
                  }}\n",
-                header_includes,
+                self.header_includes(&headers),
                 main_body)
     }
 
+    /// Utility to write `#include` lines for the probe's own headers, followed
+    /// by any extra `headers`.
+    fn header_includes(&self, headers: &[&str]) -> String {
+        let mut header_includes = String::new();
+        for header in &self.headers {
+            writeln!(&mut header_includes, "#include {}", header).unwrap();
+        }
+        for header in headers {
+            writeln!(&mut header_includes, "#include {}", header).unwrap();
+        }
+        header_includes
+    }
+
     /// Utility for code that simply prints a Rust constant, readable using
     /// `FromStr::from_str`, in `main`.
     fn run_to_get_rust_constant<T: FromStr>(&self,
                                             headers: Vec<&str>,
                                             main_body: &str)
                                             -> CProbeResult<T> {
-        let source = self.main_source_template(headers, &main_body);
-        let compile_run_output = try!(self.check_run(&source));
-        let run_out_string = try!(compile_run_output.successful_run_output());
+        let source = self.main_source_template(headers, main_body);
+        let compile_run_output = self.check_run(&source)?;
+        let run_out_string =
+            compile_run_output.successful_run_output_for(&source)?;
         // If the program produces invalid output, we don't really check what's
         // wrong with the output right now. Either the lossy UTF-8 conversion
         // will produce nonsense, or we will just fail to pick out a number
@@ -485,11 +740,7 @@ impl<'a> Probe<'a> {
 
     /// Get the size of a C type, in bytes.
     pub fn size_of(&self, type_: &str) -> CProbeResult<u64> {
-        let headers = vec!["<stdio.h>"];
-        let main_body = format!("printf(\"%zd\\n\", sizeof({}));\n\
-                                 return 0;",
-                                type_);
-        self.run_to_get_rust_constant(headers, &main_body)
+        self.evaluate_u64(Query::SizeOf(type_.to_string()))
     }
 
     /// Get the alignment of a C type, in bytes.
@@ -497,11 +748,7 @@ impl<'a> Probe<'a> {
     /// Note that this method depends on the compiler having implemented C11
     /// alignment facilities (specifically `stdalign.h` and `alignof`).
     pub fn align_of(&self, type_: &str) -> CProbeResult<u64> {
-        let headers = vec!["<stdio.h>", "<stdalign.h>"];
-        let main_body = format!("printf(\"%zd\\n\", alignof({}));\n\
-                                 return 0;",
-                                type_);
-        self.run_to_get_rust_constant(headers, &main_body)
+        self.evaluate_u64(Query::AlignOf(type_.to_string()))
     }
 
     /// Check to see if a macro is defined.
@@ -515,29 +762,19 @@ impl<'a> Probe<'a> {
     /// the API documentation, or differs between library versions. In such
     /// cases, bindings may have to omit functionality provided by macros, or
     /// else implement such functionality via some special workaround.
+    ///
+    /// If the probe has a preprocess command, it is used to list the defined
+    /// macros, regardless of the evaluation strategy.
     pub fn is_defined_macro(&self, token: &str) -> CProbeResult<bool> {
-        let headers = vec!["<stdio.h>"];
-        let main_body = format!("#ifdef {}\n\
-                                 printf(\"true\\n\");\n\
-                                 #else\n\
-                                 printf(\"false\\n\");\n\
-                                 #endif\n\
-                                 return 0;",
-                                token);
-        self.run_to_get_rust_constant(headers, &main_body)
+        if self.preprocess.is_some() {
+            return Ok(self.macro_definition(token)?.is_some());
+        }
+        self.evaluate_bool(Query::IsDefinedMacro(token.to_string()))
     }
 
     /// Check to see if an integer type is signed or unsigned.
     pub fn is_signed(&self, type_: &str) -> CProbeResult<bool> {
-        let headers = vec!["<stdio.h>"];
-        let main_body = format!("if ((({})-1) < 0) {{\n\
-                                 printf(\"true\\n\");\n\
-                                 }} else {{\n\
-                                 printf(\"false\\n\");\n\
-                                 }}\n\
-                                 return 0;",
-                                type_);
-        self.run_to_get_rust_constant(headers, &main_body)
+        self.evaluate_bool(Query::IsSigned(type_.to_string()))
     }
 
     /// Get the value of a signed integer constant defined in a header file.
@@ -547,13 +784,10 @@ impl<'a> Probe<'a> {
     /// a value set at run time during normal library use.
     ///
     /// Since this function returns an `i64`, it cannot handle values that
-    /// require a larger representation, e.g. 128-bit integers.
+    /// require a larger representation, e.g. 128-bit integers. For those, use
+    /// `wide_signed_integer_constant`.
     pub fn signed_integer_constant(&self, constant: &str) -> CProbeResult<i64> {
-        let headers = vec!["<stdio.h>"];
-        let main_body = format!("printf(\"%lld\\n\", (long long) {});\n\
-                                 return 0;",
-                                constant);
-        self.run_to_get_rust_constant(headers, &main_body)
+        self.evaluate_i64(Query::SignedIntegerConstant(constant.to_string()))
     }
 
     /// Get the value of an unsigned integer constant defined in a header file.
@@ -561,15 +795,11 @@ impl<'a> Probe<'a> {
     /// See also the documentation for the signed version of this function.
     ///
     /// Since this function returns a `u64`, it cannot represent values outside
-    /// of the range of a `u64`.
+    /// of the range of a `u64`. For those, use
+    /// `wide_unsigned_integer_constant`.
     pub fn unsigned_integer_constant(&self, constant: &str)
                                      -> CProbeResult<u64> {
-        let headers = vec!["<stdio.h>"];
-        let main_body = format!("printf(\"%llu\\n\", \
-                                        (unsigned long long) {});\n\
-                                 return 0;",
-                                constant);
-        self.run_to_get_rust_constant(headers, &main_body)
+        self.evaluate_u64(Query::UnsignedIntegerConstant(constant.to_string()))
     }
 
     /// Get the Rust type corresponding to a C integer type.
@@ -583,19 +813,12 @@ impl<'a> Probe<'a> {
     /// `u64`, but will never be `usize`.
     ///
     /// If `Ok(None)` is returned, the type is not the same size as any Rust
-    /// integer. This may happen if, for instance, the C compiler supports
-    /// 128-bit integers, which are not (as of this writing) in the Rust
-    /// prelude.
+    /// integer. 128-bit types such as GCC's `__int128` map to `i128` and
+    /// `u128`.
     pub fn equivalent_rust_integer(&self, type_: &str)
                                    -> CProbeResult<Option<String>> {
-        let signed_prefix = if try!(self.is_signed(type_)) { "i" } else { "u" };
-        let size_of_type = 8 * try!(self.size_of(type_));
-        // If there is no equivalent Rust type, return `None`.
-        match size_of_type {
-            8 | 16 | 32 | 64 => {}
-            _ => { return Ok(None); }
-        };
-        Ok(Some(format!("{}{}", signed_prefix, size_of_type)))
+        let signed = self.is_signed(type_)?;
+        Ok(rust_integer_name(signed, self.size_of(type_)?))
     }
 
     /// Get the Rust type corresponding to a C float type.
@@ -605,7 +828,7 @@ impl<'a> Probe<'a> {
     /// types we can discover here are `f32` and `f64`.
     pub fn equivalent_rust_float(&self, type_: &str)
                                  -> CProbeResult<Option<String>> {
-        let size_of_type = 8 * try!(self.size_of(type_));
+        let size_of_type = 8 * self.size_of(type_)?;
         // If there is no equivalent Rust type, return `None`.
         match size_of_type {
             32 | 64 => {}
@@ -613,6 +836,44 @@ impl<'a> Probe<'a> {
         };
         Ok(Some(format!("f{}", size_of_type)))
     }
+
+    /// Check to see if an arithmetic type is a floating-point type.
+    pub fn is_floating(&self, type_: &str) -> CProbeResult<bool> {
+        self.evaluate_bool(Query::IsFloating(type_.to_string()))
+    }
+
+    /// Get the size of a C type, and classify it as an integer, a
+    /// floating-point type, or something else.
+    ///
+    /// Any type that can't be converted from a floating-point value (e.g.
+    /// pointers, arrays and structs) is classified as `CUnknown`.
+    pub fn c_type(&self, type_: &str) -> CProbeResult<CType> {
+        let size = self.size_of(type_)?;
+        let kind = match self.is_floating(type_) {
+            Ok(true) => CFloat,
+            Ok(false) => CInteger{ signed: self.is_signed(type_)? },
+            Err(CompileError(..)) => CUnknown,
+            Err(error) => { return Err(error); }
+        };
+        Ok(CType {
+            size,
+            kind,
+        })
+    }
+}
+
+// Name of the Rust integer type with the given signedness and size in bytes,
+// if there is one.
+fn rust_integer_name(signed: bool, size: u64) -> Option<String> {
+    let signed_prefix = if signed { "i" } else { "u" };
+    let size_of_type = 8 * size;
+    // If there is no equivalent Rust type, return `None`.
+    match size_of_type {
+        8 | 16 | 32 | 64 | 128 => {
+            Some(format!("{}{}", signed_prefix, size_of_type))
+        }
+        _ => None,
+    }
 }
 
 // Little utility to cat something to a new file.
@@ -620,12 +881,13 @@ fn write_to_new_file(path: &Path, text: &str) -> io::Result<()> {
     // FIXME? Should we try putting in tests for each potential `try!` error?
     // It's hard to trigger them with Rust 1.0, since the standard library's
     // filesystem permission operations haven't been stabilized yet.
-    let mut file = try!(fs::File::create(path));
+    let mut file = fs::File::create(path)?;
     write!(&mut file, "{}", text)
 }
 
 /// We provide a default `Probe<'static>` that runs in an OS-specific temporary
-/// directory, uses gcc, and simply runs each test.
+/// directory, uses `gcc` with default options (see `Probe::with_compiler`),
+/// and simply runs each test.
 ///
 /// # Panics
 ///
@@ -634,17 +896,7 @@ fn write_to_new_file(path: &Path, text: &str) -> io::Result<()> {
 /// FIXME? Can we do better than the gcc command on Windows?
 impl Default for Probe<'static> {
     fn default() -> Self {
-        Probe::new(
-            vec![],
-            &env::temp_dir(),
-            |source_path, exe_path| {
-                Command::new("gcc").arg(source_path)
-                                   .arg("-o").arg(exe_path)
-                                   .output()
-            },
-            |exe_path| {
-                Command::new(exe_path).output()
-            },
-        ).unwrap()
+        Probe::with_compiler(vec![], &env::temp_dir(),
+                             GccCompatible::default()).unwrap()
     }
 }
@@ -0,0 +1,266 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retrieval of macro definitions.
+
+use super::{CProbeResult, Probe};
+use super::CProbeError::*;
+
+// C99 only guarantees support for this many macro parameters.
+const MAX_MACRO_PARAMETERS: usize = 127;
+
+// Macros that turn their (fully expanded) arguments into a string literal.
+// These are variadic, so that expansions containing commas are passed along
+// intact.
+const STRINGIFY_DEFINITIONS: &str =
+    "#define PROBE_C_API_STRINGIFY_(...) #__VA_ARGS__\n\
+     #define PROBE_C_API_STRINGIFY(...) PROBE_C_API_STRINGIFY_(__VA_ARGS__)\n";
+
+/// Whether a macro takes arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MacroKind {
+    /// A macro defined without a parameter list, e.g. `#define A 1`.
+    ObjectLike,
+    /// A macro defined with a parameter list, e.g. `#define F(x) (x)`.
+    FunctionLike {
+        /// The names of the named parameters.
+        parameters: Vec<String>,
+        /// Whether the parameter list ends with `...`.
+        variadic: bool,
+    },
+}
+
+/// A macro's kind, along with the text that it expands to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MacroExpansion {
+    /// Whether the macro is object-like or function-like.
+    pub kind: MacroKind,
+    /// The fully expanded replacement text, with tokens separated by single
+    /// spaces where the definition had whitespace.
+    pub expansion: String,
+}
+
+// Read the string literal at the start of `text`, undoing the escapes added
+// by stringification.
+fn parse_stringified(text: &str) -> Option<String> {
+    let mut chars = text.strip_prefix('"')?.chars();
+    let mut string = String::new();
+    loop {
+        match chars.next()? {
+            '"' => { return Some(string); }
+            '\\' => { string.push(chars.next()?); }
+            c => { string.push(c); }
+        }
+    }
+}
+
+// Split a function-like macro definition, as printed by `cc -dM -E` without
+// the `#define `, into the parameter list and whether it is variadic. Returns
+// `None` for an object-like macro.
+fn parse_parameter_list(definition: &str, name: &str)
+                        -> Option<(Vec<String>, bool)> {
+    let rest = definition[name.len()..].strip_prefix('(')?;
+    let list = &rest[..rest.find(')')?];
+    let mut parameters = Vec::new();
+    let mut variadic = false;
+    for parameter in list.split(',').map(str::trim) {
+        if parameter.is_empty() {
+            continue;
+        }
+        // GNU C allows the variadic arguments to be named, e.g. `args...`.
+        if parameter.ends_with("...") {
+            variadic = true;
+        } else {
+            parameters.push(parameter.to_string());
+        }
+    }
+    Some((parameters, variadic))
+}
+
+// Placeholder names for the parameters of a function-like macro.
+fn placeholder_parameters(count: usize) -> Vec<String> {
+    (0..count).map(|index| format!("_{}", index)).collect()
+}
+
+impl<'a> Probe<'a> {
+    // Source that stringifies `tokens` into the variable
+    // `probe_c_api_expansion`.
+    fn stringify_source(&self, tokens: &str, main_body: &str) -> String {
+        format!("{}\n\
+                 {}\
+                 const char *probe_c_api_expansion = \
+                     PROBE_C_API_STRINGIFY({});\n\
+                 int main(void) {{\n\
+                 {}\n\
+                 }}\n",
+                self.header_includes(&["<stdio.h>"]),
+                STRINGIFY_DEFINITIONS,
+                tokens,
+                main_body)
+    }
+
+    // Get the text that `tokens` expand to, by stringifying them in a probe
+    // program.
+    fn stringified_expansion(&self, tokens: &str) -> CProbeResult<String> {
+        let source = self.stringify_source(
+            tokens,
+            "fputs(probe_c_api_expansion, stdout);\n\
+             return 0;");
+        let compile_run_output = self.check_run(&source)?;
+        compile_run_output.successful_run_output_for(&source)
+    }
+
+    // Check whether a macro can be invoked with `count` arguments. Zero
+    // arguments means an empty argument list, i.e. `name()`.
+    fn accepts_arguments(&self, name: &str, count: usize)
+                         -> CProbeResult<bool> {
+        let arguments = placeholder_parameters(count).join(", ");
+        let source = self.stringify_source(&format!("{}({})", name, arguments),
+                                           "return 0;");
+        Ok(self.check_compile(&source)?.status.success())
+    }
+
+    // Find a macro's definition and expansion using only the preprocess
+    // command.
+    fn macro_expansion_from_preprocessor(
+        &self, name: &str) -> CProbeResult<Option<MacroExpansion>> {
+        let definition = match self.macro_definition(name)? {
+            Some(definition) => definition,
+            None => { return Ok(None); }
+        };
+        let (kind, tokens) = match parse_parameter_list(&definition, name) {
+            Some((parameters, variadic)) => {
+                let mut arguments = parameters.clone();
+                if variadic {
+                    arguments.push("...".to_string());
+                }
+                (MacroKind::FunctionLike { parameters, variadic },
+                 format!("{}({})", name, arguments.join(", ")))
+            }
+            None => (MacroKind::ObjectLike, name.to_string()),
+        };
+        let preprocessed = self.preprocess(
+            &self.stringify_source(&tokens, "return 0;"))?;
+        let marker = "probe_c_api_expansion =";
+        let expansion = preprocessed.rfind(marker).and_then(|start| {
+            parse_stringified(preprocessed[start + marker.len()..].trim_start())
+        });
+        match expansion {
+            Some(expansion) => Ok(Some(MacroExpansion { kind, expansion })),
+            None => Err(OtherError("unexpected output from preprocess command"
+                                   .to_string())),
+        }
+    }
+
+    /// Get the kind of a macro, and the text that it expands to.
+    ///
+    /// If `name` is not a defined macro, `Ok(None)` is returned. The
+    /// expansion of a function-like macro is that of an invocation with its
+    /// parameter names as arguments, and `...` as any variadic arguments. Any
+    /// other macros used in the definition are expanded too.
+    ///
+    /// If the probe has a preprocess command, the definition is read from the
+    /// list of defined macros, so the real parameter names are returned.
+    ///
+    /// Otherwise, the expansion is found by stringifying the macro in a probe
+    /// program, which is run regardless of the probe's `EvaluationStrategy`.
+    /// For a function-like macro, the parameter list is found by checking
+    /// which numbers of arguments the preprocessor accepts. Since the
+    /// parameters' real names can't be recovered this way, they are given the
+    /// placeholder names `_0`, `_1` and so on. Nor can the preprocessor tell
+    /// us everything else about a definition this way: a variadic macro that
+    /// ignores its arguments is reported as having a single named parameter,
+    /// and a macro that expands to its own name (e.g. `#define A A`) is
+    /// reported as object-like, even if it is actually a variadic
+    /// function-like macro.
+    pub fn macro_expansion(&self, name: &str)
+                           -> CProbeResult<Option<MacroExpansion>> {
+        if self.preprocess.is_some() {
+            return self.macro_expansion_from_preprocessor(name);
+        }
+        if !self.is_defined_macro(name)? {
+            return Ok(None);
+        }
+        let expansion = self.stringified_expansion(name)?;
+        // A function-like macro is not expanded unless it is followed by an
+        // argument list.
+        if expansion != name {
+            return Ok(Some(MacroExpansion {
+                kind: MacroKind::ObjectLike,
+                expansion,
+            }));
+        }
+        let mut first_accepted = None;
+        for count in 0..MAX_MACRO_PARAMETERS + 1 {
+            if self.accepts_arguments(name, count)? {
+                first_accepted = Some(count);
+                break;
+            }
+        }
+        let first_accepted = match first_accepted {
+            Some(count) => count,
+            None => {
+                return Err(OtherError(format!("could not find the number of \
+                                               parameters of {}",
+                                              name)));
+            }
+        };
+        // An empty argument list is also a single empty argument, so it is
+        // accepted by macros with no parameters or with one parameter.
+        let (parameter_count, variadic) = if first_accepted == 0 {
+            if !self.accepts_arguments(name, 1)? {
+                (0, false)
+            } else if !self.accepts_arguments(name, 2)? {
+                (1, false)
+            } else {
+                // A variadic macro with no named parameters passes along the
+                // first comma as part of `__VA_ARGS__`, which keeps the
+                // whitespace around it when stringified.
+                let packed = format!("{}(_0,_1)", name);
+                let spaced = format!("{}(_0, _1)", name);
+                if self.stringified_expansion(&packed)?
+                    != self.stringified_expansion(&spaced)? {
+                    (0, true)
+                } else {
+                    (1, true)
+                }
+            }
+        } else {
+            (first_accepted,
+             self.accepts_arguments(name, first_accepted + 1)?)
+        };
+        let parameters = placeholder_parameters(parameter_count);
+        let mut arguments = parameters.clone();
+        if variadic {
+            arguments.push("...".to_string());
+        }
+        let invocation = format!("{}({})", name, arguments.join(", "));
+        let expansion = self.stringified_expansion(&invocation)?;
+        // An object-like macro that expands to its own name accepts any
+        // arguments, and leaves them alone.
+        if variadic && parameter_count == 0 && expansion == invocation {
+            return Ok(Some(MacroExpansion {
+                kind: MacroKind::ObjectLike,
+                expansion: name.to_string(),
+            }));
+        }
+        Ok(Some(MacroExpansion {
+            kind: MacroKind::FunctionLike {
+                parameters,
+                variadic,
+            },
+            expansion,
+        }))
+    }
+}
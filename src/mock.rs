@@ -0,0 +1,224 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A scripted backend for testing code that uses a `Probe`, without a C
+//! compiler.
+//!
+//! This is only available on Unix and Windows, where a scripted exit status can
+//! be created.
+
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output};
+use std::sync::{Arc, Mutex};
+
+use super::{CommandResult, NewProbeError, Probe};
+
+// Exit statuses can only be created in a platform-specific way, so the mock
+// is only available on Unix and Windows.
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+/// What happens when a `MockBackend` is asked to build one program.
+#[derive(Clone, Debug)]
+pub struct MockOutcome {
+    // The compiler's output, or the message of an I/O error that prevents it
+    // from starting.
+    compile: Result<Output, String>,
+    // The program's output, if it can be run.
+    run: Option<Output>,
+}
+
+impl MockOutcome {
+    /// Create the output of a command that exited with `code`.
+    pub fn output(code: i32, stdout: &str, stderr: &str) -> Output {
+        Output {
+            status: exit_status(code),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    /// An outcome with the given compiler output and, if the program is run,
+    /// program output. If `run_output` is `None`, running the program is an
+    /// I/O error.
+    pub fn new(compile_output: Output, run_output: Option<Output>)
+               -> MockOutcome {
+        MockOutcome {
+            compile: Ok(compile_output),
+            run: run_output,
+        }
+    }
+
+    /// The program compiles, and prints `stdout` when run.
+    pub fn runs(stdout: &str) -> MockOutcome {
+        MockOutcome::new(MockOutcome::output(0, "", ""),
+                         Some(MockOutcome::output(0, stdout, "")))
+    }
+
+    /// The program compiles, and prints nothing when run.
+    pub fn compiles() -> MockOutcome {
+        MockOutcome::runs("")
+    }
+
+    /// The program fails to compile, and the compiler prints `stderr`.
+    ///
+    /// The diagnostics in `stderr` are parsed as usual (see
+    /// `parse_diagnostics`), so a diagnostic for a file named like
+    /// `source-1.c` is matched to a line of the generated program.
+    pub fn compile_error(stderr: &str) -> MockOutcome {
+        MockOutcome::new(MockOutcome::output(1, "", stderr), None)
+    }
+
+    /// The program compiles, but exits with `code` and prints `stderr` when
+    /// run.
+    pub fn run_error(code: i32, stderr: &str) -> MockOutcome {
+        MockOutcome::new(MockOutcome::output(0, "", ""),
+                         Some(MockOutcome::output(code, "", stderr)))
+    }
+
+    /// The compiler can't be started, which is reported as an I/O error with
+    /// the given message.
+    pub fn io_error(message: &str) -> MockOutcome {
+        MockOutcome {
+            compile: Err(message.to_string()),
+            run: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    patterns: Vec<(String, MockOutcome)>,
+    queue: VecDeque<MockOutcome>,
+    sources: Vec<String>,
+    // Outputs of programs that were compiled, but have not been run yet.
+    pending_runs: HashMap<PathBuf, Option<Output>>,
+}
+
+/// A backend for a `Probe` that answers from a script, rather than running a
+/// compiler, so that error paths can be tested without a C toolchain.
+///
+/// Each program that the probe asks to build is matched against the
+/// outcomes given to `when_contains`, in the order they were given, and
+/// otherwise takes the next outcome given to `then`. A program that matches
+/// nothing is reported as an I/O error. Every source is recorded, and can be
+/// checked afterward with `sources`.
+///
+/// Clones share the same script, so a clone can be kept to inspect the
+/// backend after it has been moved into a probe.
+#[derive(Clone, Debug, Default)]
+pub struct MockBackend {
+    script: Arc<Mutex<Script>>,
+}
+
+impl MockBackend {
+    /// Create a backend with an empty script.
+    pub fn new() -> MockBackend {
+        MockBackend::default()
+    }
+
+    /// Use `outcome` for every program whose source contains `pattern`.
+    pub fn when_contains(&self, pattern: &str, outcome: MockOutcome)
+                         -> &MockBackend {
+        self.script.lock().unwrap()
+                   .patterns.push((pattern.to_string(), outcome));
+        self
+    }
+
+    /// Use `outcome` once, for the next program that doesn't match a pattern
+    /// given to `when_contains`.
+    pub fn then(&self, outcome: MockOutcome) -> &MockBackend {
+        self.script.lock().unwrap().queue.push_back(outcome);
+        self
+    }
+
+    /// The sources of the programs built so far, in order.
+    pub fn sources(&self) -> Vec<String> {
+        self.script.lock().unwrap().sources.clone()
+    }
+
+    /// The number of outcomes given to `then` that have not been used yet.
+    pub fn remaining(&self) -> usize {
+        self.script.lock().unwrap().queue.len()
+    }
+
+    /// Create a probe that uses this backend, with its work directory in the
+    /// OS-specific temporary directory. Generated sources are still written
+    /// there, but nothing is compiled or run.
+    pub fn probe(&self, headers: Vec<String>)
+                 -> Result<Probe<'static>, NewProbeError> {
+        let compiler = self.clone();
+        let runner = self.clone();
+        Probe::new(
+            headers,
+            &env::temp_dir(),
+            move |source_path, exe_path| {
+                compiler.compile(source_path, exe_path)
+            },
+            move |exe_path| runner.run(exe_path),
+        )
+    }
+
+    fn compile(&self, source_path: &Path, exe_path: &Path) -> CommandResult {
+        let source = fs::read_to_string(source_path)?;
+        let outcome = {
+            let mut script = self.script.lock().unwrap();
+            script.sources.push(source.clone());
+            let matched = script.patterns.iter().find(|pattern| {
+                source.contains(&pattern.0[..])
+            }).map(|pattern| pattern.1.clone());
+            match matched.or_else(|| script.queue.pop_front()) {
+                Some(outcome) => outcome,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "no outcome scripted for probe program"));
+                }
+            }
+        };
+        let output = outcome.compile.map_err(|message| {
+            io::Error::new(io::ErrorKind::Other, message)
+        })?;
+        if output.status.success() {
+            // The probe removes the program after running it.
+            fs::File::create(exe_path)?;
+            self.script.lock().unwrap()
+                       .pending_runs.insert(exe_path.to_path_buf(),
+                                            outcome.run);
+        }
+        Ok(output)
+    }
+
+    fn run(&self, exe_path: &Path) -> CommandResult {
+        let run = self.script.lock().unwrap().pending_runs.remove(exe_path);
+        match run {
+            Some(Some(output)) => Ok(output),
+            _ => Err(io::Error::new(io::ErrorKind::Other,
+                                    "no run output scripted for program")),
+        }
+    }
+}
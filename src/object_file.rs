@@ -0,0 +1,201 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Evaluation of queries by reading initialized data out of compiled files
+//! (see `EvaluationStrategy::ObjectFile`).
+
+use std::fmt::Write as FormatWrite;
+use std::fs;
+use std::io;
+use std::process;
+
+use super::{write_to_new_file, CProbeResult, Probe};
+use super::CProbeError::*;
+use diagnostics::compile_error;
+use cache::CachedOutputs;
+use elf::ElfFile;
+use query::{query_headers, BatchAttempt, Query, QueryValue, ValueKind};
+
+/// Byte order of a compilation target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    /// Least significant byte first.
+    Little,
+    /// Most significant byte first.
+    Big,
+}
+
+impl Endianness {
+    /// Decode an unsigned integer of up to eight bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than eight bytes are provided.
+    pub fn decode(self, bytes: &[u8]) -> u64 {
+        assert!(bytes.len() <= 8, "too many bytes for a u64");
+        let fold = |value: u64, &byte: &u8| (value << 8) | u64::from(byte);
+        match self {
+            Endianness::Little => bytes.iter().rev().fold(0, fold),
+            Endianness::Big => bytes.iter().fold(0, fold),
+        }
+    }
+}
+
+/// Basic facts about the target, read from the header of a compiled file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TargetInfo {
+    /// Byte order used by the target.
+    pub endianness: Endianness,
+    /// The size of an address on the target, in bytes (4 or 8).
+    pub word_size: u64,
+}
+
+impl<'a> Probe<'a> {
+    // Compile a source file, returning the compiler output and, if compilation
+    // succeeded, the contents of the compiled file.
+    fn compile_and_read(&self, source: &str)
+                        -> CProbeResult<(process::Output, Option<Vec<u8>>)> {
+        let mut cached = self.cached("object", source, || {
+            let (source_path, exe_path) = self.random_source_and_exe_paths();
+            write_to_new_file(&source_path, source)?;
+            let compile = self.compile_object.as_ref()
+                                             .unwrap_or(&self.compile_to);
+            let compile_output = (**compile)(&source_path, &exe_path)?;
+            fs::remove_file(&source_path)?;
+            let contents = if compile_output.status.success() {
+                Some(fs::read(&exe_path)?)
+            } else {
+                None
+            };
+            match fs::remove_file(&exe_path) {
+                Ok(..) => {}
+                Err(error) => {
+                    if error.kind() != io::ErrorKind::NotFound {
+                        return Err(error);
+                    }
+                }
+            }
+            Ok(CachedOutputs {
+                outputs: vec![compile_output],
+                contents,
+            })
+        })?;
+        Ok((cached.outputs.remove(0), cached.contents))
+    }
+
+    // Compile `source`, which must define the global variables that will be
+    // read, and parse the compiled file. Compilation failure is reported in
+    // the inner result.
+    pub(crate) fn compile_to_elf<T, F>(&self, source: &str, read: F)
+                            -> CProbeResult<CProbeResult<T>>
+        where F: FnOnce(&ElfFile) -> CProbeResult<T> {
+        let (compile_output, contents) = self.compile_and_read(source)?;
+        let contents = match contents {
+            Some(contents) => contents,
+            None => {
+                return Ok(Err(compile_error(compile_output, source)));
+            }
+        };
+        match ElfFile::parse(&contents) {
+            Ok(elf) => Ok(read(&elf)),
+            Err(message) => {
+                Ok(Err(OtherError(format!("values can only be read from \
+                                           compiled ELF files: {}",
+                                          message))))
+            }
+        }
+    }
+
+    // Answer a batch of queries from a single compiled file.
+    pub(crate) fn object_file_batch(&self, queries: &[Query])
+                                    -> CProbeResult<BatchAttempt> {
+        let mut source = self.header_includes(&query_headers(queries));
+        for (label, query) in queries.iter().enumerate() {
+            let expression = query.expression(label);
+            let expression = match query.value_kind() {
+                ValueKind::Bool => format!("({}) ? 1 : 0", expression),
+                ValueKind::Signed | ValueKind::Unsigned => expression,
+            };
+            source.push_str(&query.prelude(label));
+            writeln!(&mut source,
+                     "unsigned long long probe_c_api_value_{} = \
+                      (unsigned long long) ({});",
+                     label, expression).unwrap();
+        }
+        source.push_str("int main(void) { return 0; }\n");
+        let (compile_output, contents) = self.compile_and_read(&source)?;
+        let contents = match contents {
+            Some(contents) => contents,
+            None => {
+                return Ok(Err(compile_error(compile_output, &source)));
+            }
+        };
+        // If the file can't be parsed, splitting up the batch won't help, so
+        // report the problem for each query instead.
+        let elf = ElfFile::parse(&contents);
+        Ok(Ok(queries.iter().enumerate().map(|(label, query)| {
+            let elf = elf.as_ref().map_err(|message| {
+                OtherError(message.clone())
+            })?;
+            let name = format!("probe_c_api_value_{}", label);
+            let bytes = elf.symbol_contents(&name).map_err(OtherError)?;
+            if bytes.len() != 8 {
+                return Err(OtherError(format!("{} has unexpected size {}",
+                                              name, bytes.len())));
+            }
+            let value = elf.endianness.decode(&bytes);
+            Ok(match query.value_kind() {
+                ValueKind::Bool => QueryValue::Bool(value != 0),
+                ValueKind::Signed => QueryValue::Signed(value as i64),
+                ValueKind::Unsigned => QueryValue::Unsigned(value),
+            })
+        }).collect()))
+    }
+
+    /// Get the bytes that represent a value of a given C type, as stored in the
+    /// target's memory.
+    ///
+    /// This compiles a global variable of type `type_`, initialized with
+    /// `initializer` (which must be a constant initializer), then reads the
+    /// variable's initial contents out of the compiled file. Nothing is run,
+    /// regardless of the probe's `EvaluationStrategy`, but the object file
+    /// command (see `set_compile_object`), or else the `compile_to` command,
+    /// must produce an ELF file with a symbol table.
+    pub fn object_representation(&self, type_: &str, initializer: &str)
+                                 -> CProbeResult<Vec<u8>> {
+        let source = format!("{}\n\
+                              {} probe_c_api_object = {};\n\
+                              int main(void) {{ return 0; }}\n",
+                             self.header_includes(&[]),
+                             type_,
+                             initializer);
+        self.compile_to_elf(&source, |elf| {
+            elf.symbol_contents("probe_c_api_object").map_err(OtherError)
+        })?
+    }
+
+    /// Get the byte order and word size of the target, from the header of a
+    /// compiled file. As with `object_representation`, this requires ELF
+    /// output, but does not run anything.
+    pub fn target_info(&self) -> CProbeResult<TargetInfo> {
+        let source = format!("{}\nint main(void) {{ return 0; }}\n",
+                             self.header_includes(&[]));
+        self.compile_to_elf(&source, |elf| {
+            Ok(TargetInfo {
+                endianness: elf.endianness,
+                word_size: if elf.is_64_bit { 8 } else { 4 },
+            })
+        })?
+    }
+}
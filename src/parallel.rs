@@ -0,0 +1,141 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Answering independent queries on several threads at once.
+
+use std::panic;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use super::{CompileCommand, CProbeResult, PreprocessCommand, Probe};
+use jobserver::{Jobserver, TokenPool};
+use preprocess::PreprocessMode;
+use query::{Query, QueryValue};
+
+// Wrap a compile command so that it holds a token from `pool` while it runs.
+fn compile_with_token<'p>(compile: CompileCommand<'p>, pool: &'p TokenPool)
+                          -> CompileCommand<'p> {
+    Arc::new(move |source_path: &Path, output_path: &Path| {
+        pool.with_token(|| compile(source_path, output_path))
+    })
+}
+
+impl<'a> Probe<'a> {
+    /// Answer independent queries using up to `threads` worker threads, each
+    /// evaluating one query at a time, as with `evaluate`.
+    ///
+    /// The results are returned in the same order as the queries. Unlike
+    /// `evaluate_batch`, each query gets its own probe program(s), so this is
+    /// most useful when programs are slow to compile or run, or with
+    /// `EvaluationStrategy::CompileOnly`, which needs many compilations per
+    /// query. Concurrent probe programs are kept apart by the random file
+    /// names used in the work directory.
+    ///
+    /// If a jobserver is found in the environment (see
+    /// `Jobserver::from_env`), as it is in a Cargo build script, it is used
+    /// as with `run_parallel_with_jobserver`, so that probing does not
+    /// oversubscribe the machine. Otherwise, `threads` is the only limit. If
+    /// `threads` is zero, a single thread is used.
+    pub fn run_parallel(&self, queries: &[Query], threads: usize)
+                        -> Vec<CProbeResult<QueryValue>> {
+        match Jobserver::from_env() {
+            Some(jobserver) => {
+                self.run_parallel_with_jobserver(queries, threads, &jobserver)
+            }
+            None => evaluate_in_parallel(self, queries, threads),
+        }
+    }
+
+    /// Answer independent queries in parallel, as with `run_parallel`, but
+    /// take a token from `jobserver` before each compile, preprocess or run
+    /// command, and release it when the command is done.
+    ///
+    /// As with any jobserver client, one command at a time can be run using
+    /// the token that this process holds implicitly. At most `threads`
+    /// queries are evaluated at once, even if more tokens are available. If
+    /// the jobserver stops working, the remaining commands are run one at a
+    /// time using the implicit token, as Cargo and `make` do.
+    pub fn run_parallel_with_jobserver(&self, queries: &[Query],
+                                       threads: usize, jobserver: &Jobserver)
+                                       -> Vec<CProbeResult<QueryValue>> {
+        let pool = TokenPool::new(jobserver);
+        let probe = self.with_tokens(&pool);
+        evaluate_in_parallel(&probe, queries, threads)
+    }
+
+    // A copy of this probe whose commands each hold a token from `pool`
+    // while they run.
+    fn with_tokens<'p>(&'p self, pool: &'p TokenPool) -> Probe<'p> {
+        let run = self.run.clone();
+        let preprocess = self.preprocess.clone();
+        Probe {
+            headers: self.headers.clone(),
+            work_dir: self.work_dir.clone(),
+            compile_to: compile_with_token(self.compile_to.clone(), pool),
+            run: Arc::new(move |exe_path: &Path| {
+                pool.with_token(|| run(exe_path))
+            }),
+            compile_object: self.compile_object.clone().map(|compile| {
+                compile_with_token(compile, pool)
+            }),
+            preprocess: preprocess.map(|preprocess| -> PreprocessCommand<'p> {
+                Arc::new(move |source_path: &Path, mode: PreprocessMode| {
+                    pool.with_token(|| preprocess(source_path, mode))
+                })
+            }),
+            identify: self.identify.clone(),
+            cache: self.cache.clone(),
+            strategy: self.strategy,
+        }
+    }
+}
+
+// Evaluate queries on up to `threads` threads that share `probe`.
+fn evaluate_in_parallel(probe: &Probe, queries: &[Query], threads: usize)
+                        -> Vec<CProbeResult<QueryValue>> {
+    let threads = threads.max(1).min(queries.len().max(1));
+    let next_query = AtomicUsize::new(0);
+    let mut results: Vec<Option<CProbeResult<QueryValue>>> =
+        queries.iter().map(|_| None).collect();
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|_| {
+            scope.spawn(|| {
+                let mut answers = Vec::new();
+                loop {
+                    let index = next_query.fetch_add(1, Ordering::Relaxed);
+                    if index >= queries.len() {
+                        break;
+                    }
+                    answers.push((index, probe.evaluate(&queries[index])));
+                }
+                answers
+            })
+        }).collect();
+        for worker in workers {
+            match worker.join() {
+                Ok(answers) => {
+                    for (index, result) in answers {
+                        results[index] = Some(result);
+                    }
+                }
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        }
+    });
+    results.into_iter()
+           .map(|result| result.expect("query was not answered"))
+           .collect()
+}
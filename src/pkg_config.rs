@@ -0,0 +1,281 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Construction of probes from `pkg-config` metadata.
+
+use std::env;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::{NewProbeError, Probe};
+use builder::ProbeBuilder;
+use compiler::CompilerOptions;
+
+use self::PkgConfigError::*;
+
+/// Errors that can occur when getting settings from `pkg-config`.
+#[derive(Debug)]
+pub enum PkgConfigError {
+    /// The `pkg-config` command could not be run at all.
+    CommandFailed(io::Error),
+    /// `pkg-config` ran, but reported an error, e.g. because the package is
+    /// not installed or its version is out of range. The message printed by
+    /// `pkg-config` is included.
+    PackageError(String),
+    /// The settings were found, but the probe could not be created.
+    ProbeFailed(NewProbeError),
+}
+
+impl fmt::Display for PkgConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            CommandFailed(ref error) => {
+                f.write_fmt(
+                    format_args!("PkgConfigError: could not run pkg-config: {}",
+                                 error)
+                )
+            }
+            PackageError(ref message) => {
+                f.write_fmt(
+                    format_args!("PkgConfigError: pkg-config failed: {}",
+                                 message)
+                )
+            }
+            ProbeFailed(ref error) => {
+                f.write_fmt(format_args!("PkgConfigError: {}", error))
+            }
+        }
+    }
+}
+
+impl Error for PkgConfigError {
+    fn description(&self) -> &str {
+        match *self {
+            CommandFailed(..) => "could not run pkg-config",
+            PackageError(..) => "pkg-config reported an error",
+            ProbeFailed(..) => "could not create a probe",
+        }
+    }
+    fn cause(&self) -> Option<&dyn Error> {
+        match *self {
+            CommandFailed(ref error) => Some(error),
+            PackageError(..) => None,
+            ProbeFailed(ref error) => Some(error),
+        }
+    }
+}
+
+/// Compiler settings and version of a package, as found by `pkg-config`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PkgConfigLibrary {
+    /// The package's version.
+    pub version: String,
+    /// Include directories, defines and other flags from `--cflags`. The
+    /// output of `--libs` is kept unchanged, in order, in `link_flags`, since
+    /// the meaning of flags such as `-Wl,--whole-archive` depends on where
+    /// they appear.
+    pub options: CompilerOptions,
+}
+
+/// A query for a package's settings, to be answered by `pkg-config`.
+///
+/// The `pkg-config` command can be overridden with the `PKG_CONFIG`
+/// environment variable.
+#[derive(Clone, Debug)]
+pub struct PkgConfig {
+    package: String,
+    min_version: Option<String>,
+    max_version: Option<String>,
+    search_dirs: Vec<PathBuf>,
+}
+
+// Split a list of flags printed by `pkg-config`, which escapes spaces within
+// a flag with backslashes.
+fn split_flags(text: &str) -> Vec<String> {
+    let mut flags = Vec::new();
+    let mut flag = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    flag.push(escaped);
+                }
+            }
+            c if c.is_whitespace() => {
+                if !flag.is_empty() {
+                    flags.push(flag);
+                    flag = String::new();
+                }
+            }
+            c => { flag.push(c); }
+        }
+    }
+    if !flag.is_empty() {
+        flags.push(flag);
+    }
+    flags
+}
+
+// Sort flags from `--cflags` into the matching `CompilerOptions`.
+fn add_cflags(options: &mut CompilerOptions, flags: Vec<String>) {
+    for flag in flags {
+        if let Some(dir) = flag.strip_prefix("-I") {
+            options.include_dirs.push(PathBuf::from(dir));
+        } else if let Some(define) = flag.strip_prefix("-D") {
+            let mut parts = define.splitn(2, '=');
+            let name = parts.next().unwrap_or("").to_string();
+            options.defines.push((name, parts.next().map(str::to_string)));
+        } else {
+            options.flags.push(flag);
+        }
+    }
+}
+
+impl PkgConfig {
+    /// Look up a package, e.g. `libfoo`, with any version.
+    pub fn new(package: &str) -> PkgConfig {
+        PkgConfig {
+            package: package.to_string(),
+            min_version: None,
+            max_version: None,
+            search_dirs: Vec::new(),
+        }
+    }
+
+    /// Require at least the given version.
+    pub fn min_version(mut self, version: &str) -> PkgConfig {
+        self.min_version = Some(version.to_string());
+        self
+    }
+
+    /// Require a version lower than the given one (i.e. an exclusive upper
+    /// bound).
+    pub fn max_version(mut self, version: &str) -> PkgConfig {
+        self.max_version = Some(version.to_string());
+        self
+    }
+
+    /// Search a directory for `.pc` files before the ones that `pkg-config`
+    /// would otherwise search, as if it were listed in `PKG_CONFIG_PATH`.
+    pub fn search_dir(mut self, dir: &Path) -> PkgConfig {
+        self.search_dirs.push(dir.to_path_buf());
+        self
+    }
+
+    // Run `pkg-config` with the given option, returning its output.
+    fn run(&self, option: &str) -> Result<String, PkgConfigError> {
+        let program = env::var_os("PKG_CONFIG")
+                          .unwrap_or_else(|| OsString::from("pkg-config"));
+        let mut command = Command::new(program);
+        if !self.search_dirs.is_empty() {
+            let mut dirs = self.search_dirs.clone();
+            if let Some(path) = env::var_os("PKG_CONFIG_PATH") {
+                dirs.extend(env::split_paths(&path));
+            }
+            let path = env::join_paths(dirs).map_err(|error| {
+                PackageError(error.to_string())
+            })?;
+            command.env("PKG_CONFIG_PATH", path);
+        }
+        // Version constraints are written into the package list, e.g.
+        // `libfoo >= 1.0 libfoo < 2.0`.
+        let mut packages = self.package.clone();
+        if let Some(ref version) = self.min_version {
+            packages.push_str(&format!(" {} >= {}", self.package, version));
+        }
+        if let Some(ref version) = self.max_version {
+            packages.push_str(&format!(" {} < {}", self.package, version));
+        }
+        let output = command.arg("--print-errors").arg(option).arg(&packages)
+                            .output().map_err(CommandFailed)?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(PackageError(String::from_utf8_lossy(&output.stderr)
+                                 .trim().to_string()))
+        }
+    }
+
+    /// Run `pkg-config` to check the package's version and get its settings.
+    pub fn find(&self) -> Result<PkgConfigLibrary, PkgConfigError> {
+        let version = PkgConfig {
+            min_version: None,
+            max_version: None,
+            ..self.clone()
+        }.run("--modversion")?.trim().to_string();
+        let mut options = CompilerOptions::default();
+        add_cflags(&mut options, split_flags(&self.run("--cflags")?));
+        options.link_flags = split_flags(&self.run("--libs")?);
+        Ok(PkgConfigLibrary { version, options })
+    }
+}
+
+impl ProbeBuilder {
+    /// Use the settings found by `pkg-config` for a package, in addition to
+    /// any others.
+    ///
+    /// `pkg-config` does not list a package's headers, so they must still be
+    /// added with `header`.
+    pub fn pkg_config(self, library: &PkgConfigLibrary) -> ProbeBuilder {
+        let options = &library.options;
+        let mut builder = self;
+        for dir in &options.include_dirs {
+            builder = builder.include_dir(dir);
+        }
+        for (name, value) in &options.defines {
+            builder = match value {
+                Some(value) => builder.define_value(name, value),
+                None => builder.define(name),
+            };
+        }
+        for library in &options.libraries {
+            builder = builder.library(library);
+        }
+        for dir in &options.library_dirs {
+            builder = builder.library_dir(dir);
+        }
+        for flag in &options.flags {
+            builder = builder.flag(flag);
+        }
+        for flag in &options.link_flags {
+            builder = builder.link_flag(flag);
+        }
+        builder
+    }
+}
+
+impl Probe<'static> {
+    /// Create a probe that compiles and links with the settings that
+    /// `pkg-config` gives for a package, e.g. `libfoo`, and includes the
+    /// package's `headers` in every program. Otherwise, the probe has the
+    /// same settings as `Probe::default`.
+    ///
+    /// As with `Probe::new`, each header should have the `<>` or `""`
+    /// delimiters surrounding it. To check the package's version, use
+    /// `PkgConfig` and `ProbeBuilder::pkg_config` instead.
+    pub fn from_pkg_config(package: &str, headers: &[&str])
+                           -> Result<Probe<'static>, PkgConfigError> {
+        let library = PkgConfig::new(package).find()?;
+        let mut builder = Probe::builder().pkg_config(&library);
+        for header in headers {
+            builder = builder.header(header);
+        }
+        builder.build().map_err(ProbeFailed)
+    }
+}
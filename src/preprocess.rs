@@ -0,0 +1,161 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Questions that can be answered by the preprocessor alone.
+
+use std::fs;
+use std::path::PathBuf;
+
+use super::{write_to_new_file, CProbeResult, Probe};
+use super::CProbeError::*;
+use diagnostics::compile_error;
+use query::{query_headers, Query};
+
+/// The kind of output requested from a preprocess command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreprocessMode {
+    /// The preprocessed source, as produced by `cc -E`. Line markers should
+    /// be included, so that the files pulled in by each header can be found.
+    Expand,
+    /// The `#define` directives for all macros defined at the end of the
+    /// source, as produced by `cc -dM -E`.
+    DefinedMacros,
+}
+
+// Parse the file name from a line marker, e.g. `# 1 "/usr/include/stdio.h" 1`,
+// if it marks the start of a new file.
+fn entered_file(line: &str) -> Option<PathBuf> {
+    let rest = line.strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("line").unwrap_or(rest).trim_start();
+    let digits = rest.len() - rest.trim_start_matches(|c: char| {
+        c.is_ascii_digit()
+    }).len();
+    if digits == 0 {
+        return None;
+    }
+    let rest = rest[digits..].trim_start().strip_prefix('"')?;
+    let mut name = String::new();
+    let mut chars = rest.chars();
+    loop {
+        match chars.next()? {
+            '"' => { break; }
+            '\\' => { name.push(chars.next()?); }
+            c => { name.push(c); }
+        }
+    }
+    let flags: Vec<&str> = chars.as_str().split_whitespace().collect();
+    if flags.first() == Some(&"1") && !name.starts_with('<') {
+        Some(PathBuf::from(name))
+    } else {
+        None
+    }
+}
+
+impl<'a> Probe<'a> {
+    // Preprocess `source` in the given mode, returning the standard output.
+    fn preprocess_in_mode(&self, source: &str, mode: PreprocessMode)
+                          -> CProbeResult<String> {
+        let preprocess = match self.preprocess {
+            Some(ref preprocess) => preprocess,
+            None => {
+                return Err(OtherError("probe has no preprocess command"
+                                      .to_string()));
+            }
+        };
+        let (source_path, _) = self.random_source_and_exe_paths();
+        write_to_new_file(&source_path, source)?;
+        let output = (*preprocess)(&source_path, mode)?;
+        fs::remove_file(&source_path)?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(compile_error(output, source))
+        }
+    }
+
+    /// Check whether this probe has a preprocess command (see
+    /// `set_preprocess`).
+    pub fn has_preprocess(&self) -> bool {
+        self.preprocess.is_some()
+    }
+
+    /// Run only the preprocessor on `source`, returning the preprocessed text.
+    ///
+    /// The probe's headers are not added to `source`. If the probe has no
+    /// preprocess command, an `OtherError` is returned, and if preprocessing
+    /// fails, the command's output is returned in a compilation error (see
+    /// `CProbeError::compile_failure`).
+    pub fn preprocess(&self, source: &str) -> CProbeResult<String> {
+        self.preprocess_in_mode(source, PreprocessMode::Expand)
+    }
+
+    // Get the text after `#define ` in the definition of the macro `name`,
+    // after including the probe's headers, if it is defined.
+    //
+    // The headers are the same as those of the program that would answer
+    // `Query::IsDefinedMacro`, so that `is_defined_macro` gives the same
+    // answer with or without a preprocess command.
+    pub(crate) fn macro_definition(&self, name: &str)
+                                   -> CProbeResult<Option<String>> {
+        let query = Query::IsDefinedMacro(name.to_string());
+        let source = self.header_includes(&query_headers(&[query]));
+        let definitions =
+            self.preprocess_in_mode(&source, PreprocessMode::DefinedMacros)?;
+        Ok(definitions.lines().filter_map(|line| {
+            line.strip_prefix("#define ")
+        }).find(|definition| {
+            match definition.strip_prefix(name) {
+                Some(rest) => rest.is_empty() || rest.starts_with([' ', '(']),
+                None => false,
+            }
+        }).map(|definition| definition.to_string()))
+    }
+
+    /// List the names of all macros defined after including the probe's
+    /// headers, in the order reported by the preprocess command. This
+    /// includes macros predefined by the compiler.
+    pub fn defined_macros(&self) -> CProbeResult<Vec<String>> {
+        let source = self.header_includes(&[]);
+        let definitions =
+            self.preprocess_in_mode(&source, PreprocessMode::DefinedMacros)?;
+        Ok(definitions.lines().filter_map(|line| {
+            let definition = line.strip_prefix("#define ")?;
+            let end = definition.find([' ', '('])
+                                .unwrap_or(definition.len());
+            Some(definition[..end].to_string())
+        }).collect())
+    }
+
+    /// List the files pulled in by the probe's headers, directly or
+    /// indirectly, in the order in which they are first included.
+    ///
+    /// This relies on the line markers in the output of the preprocess
+    /// command. Files that the compiler includes implicitly may be listed too.
+    pub fn included_files(&self) -> CProbeResult<Vec<PathBuf>> {
+        self.files_included_by(&self.header_includes(&[]))
+    }
+
+    // List the files pulled in by `source`.
+    pub(crate) fn files_included_by(&self, source: &str)
+                                    -> CProbeResult<Vec<PathBuf>> {
+        let preprocessed = self.preprocess(source)?;
+        let mut files: Vec<PathBuf> = Vec::new();
+        for file in preprocessed.lines().filter_map(entered_file) {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+        Ok(files)
+    }
+}
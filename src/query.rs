@@ -0,0 +1,380 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Queries that can be answered one at a time, or gathered into a batch that
+//! is answered by a single probe program.
+
+use std::str::FromStr;
+
+use super::{CProbeResult, EvaluationStrategy, Probe};
+use super::CProbeError::*;
+
+/// A single question about a C API that a `Probe` can answer.
+///
+/// Each variant corresponds to one of the `Probe` methods of the same name,
+/// and carries the C type or expression that the method would take.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Query {
+    /// The size of a type, in bytes. Answered with `QueryValue::Unsigned`.
+    SizeOf(String),
+    /// The alignment of a type, in bytes. Answered with
+    /// `QueryValue::Unsigned`.
+    AlignOf(String),
+    /// Whether an integer type is signed. Answered with `QueryValue::Bool`.
+    IsSigned(String),
+    /// Whether a macro is defined. Answered with `QueryValue::Bool`.
+    IsDefinedMacro(String),
+    /// Whether an arithmetic type is a floating-point type. Answered with
+    /// `QueryValue::Bool`.
+    IsFloating(String),
+    /// The value of a signed integer constant. Answered with
+    /// `QueryValue::Signed`.
+    SignedIntegerConstant(String),
+    /// The value of an unsigned integer constant. Answered with
+    /// `QueryValue::Unsigned`.
+    UnsignedIntegerConstant(String),
+    /// The offset of a field (the second string) within a struct or union type
+    /// (the first string). Answered with `QueryValue::Unsigned`.
+    OffsetOf(String, String),
+    /// The size of a field. Answered with `QueryValue::Unsigned`.
+    FieldSizeOf(String, String),
+    /// The alignment of a field. Answered with `QueryValue::Unsigned`.
+    FieldAlignOf(String, String),
+}
+
+/// The answer to a `Query`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryValue {
+    /// Answer to a yes-or-no query.
+    Bool(bool),
+    /// A signed integer.
+    Signed(i64),
+    /// An unsigned integer, also used for sizes and alignments.
+    Unsigned(u64),
+}
+
+impl QueryValue {
+    /// Returns the value if it is a `Bool`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            QueryValue::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+    /// Returns the value if it is a `Signed` integer.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            QueryValue::Signed(value) => Some(value),
+            _ => None,
+        }
+    }
+    /// Returns the value if it is an `Unsigned` integer.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            QueryValue::Unsigned(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+// The representation that a query's C expression has to be given in order to
+// read it back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ValueKind {
+    Bool,
+    Signed,
+    Unsigned,
+}
+
+impl ValueKind {
+    // Parse the text printed by a probe program for a value of this kind.
+    pub(crate) fn parse(self, text: &str) -> Option<QueryValue> {
+        match self {
+            ValueKind::Bool => {
+                FromStr::from_str(text).ok().map(QueryValue::Bool)
+            }
+            ValueKind::Signed => {
+                FromStr::from_str(text).ok().map(QueryValue::Signed)
+            }
+            ValueKind::Unsigned => {
+                FromStr::from_str(text).ok().map(QueryValue::Unsigned)
+            }
+        }
+    }
+}
+
+impl Query {
+    pub(crate) fn value_kind(&self) -> ValueKind {
+        match *self {
+            Query::IsSigned(..) | Query::IsDefinedMacro(..) |
+            Query::IsFloating(..) => ValueKind::Bool,
+            Query::SignedIntegerConstant(..) => ValueKind::Signed,
+            Query::SizeOf(..) | Query::AlignOf(..) |
+            Query::UnsignedIntegerConstant(..) | Query::OffsetOf(..) |
+            Query::FieldSizeOf(..) | Query::FieldAlignOf(..) => {
+                ValueKind::Unsigned
+            }
+        }
+    }
+
+    // Headers, beyond those of the probe itself, that the query's expression
+    // needs.
+    pub(crate) fn headers(&self) -> Vec<&'static str> {
+        match *self {
+            Query::AlignOf(..) | Query::FieldAlignOf(..) => {
+                vec!["<stdalign.h>"]
+            }
+            Query::OffsetOf(..) => vec!["<stddef.h>"],
+            _ => vec![],
+        }
+    }
+
+    // Preprocessor lines that must precede any use of the query's expression.
+    // The `label` is used to keep names from different queries distinct.
+    pub(crate) fn prelude(&self, label: usize) -> String {
+        match *self {
+            Query::IsDefinedMacro(ref token) => {
+                format!("#ifdef {}\n\
+                         #define PROBE_C_API_DEFINED_{} 1\n\
+                         #else\n\
+                         #define PROBE_C_API_DEFINED_{} 0\n\
+                         #endif\n",
+                        token, label, label)
+            }
+            _ => String::new(),
+        }
+    }
+
+    // The C expression for this query's answer. For `ValueKind::Bool` this
+    // is a condition, otherwise it is an integer expression.
+    pub(crate) fn expression(&self, label: usize) -> String {
+        match *self {
+            Query::SizeOf(ref type_) => format!("sizeof({})", type_),
+            Query::AlignOf(ref type_) => format!("alignof({})", type_),
+            Query::IsSigned(ref type_) => format!("(({})-1) < 0", type_),
+            // Converting 0.5 to an integer type gives 0, or 1 for `_Bool`.
+            Query::IsFloating(ref type_) => {
+                format!("(({0}) 0.5) != 0 && (({0}) 0.5) != 1", type_)
+            }
+            Query::IsDefinedMacro(..) => {
+                format!("PROBE_C_API_DEFINED_{}", label)
+            }
+            Query::SignedIntegerConstant(ref constant) |
+            Query::UnsignedIntegerConstant(ref constant) => constant.clone(),
+            Query::OffsetOf(ref type_, ref field) => {
+                format!("offsetof({}, {})", type_, field)
+            }
+            Query::FieldSizeOf(ref type_, ref field) => {
+                format!("sizeof((({} *) 0)->{})", type_, field)
+            }
+            Query::FieldAlignOf(ref type_, ref field) => {
+                format!("alignof((({} *) 0)->{})", type_, field)
+            }
+        }
+    }
+
+    // A statement that prints `tag` followed by the query's answer and a
+    // newline.
+    pub(crate) fn print_statement(&self, label: usize, tag: &str) -> String {
+        let expression = self.expression(label);
+        match self.value_kind() {
+            ValueKind::Bool => {
+                format!("printf(\"{}%s\\n\", ({}) ? \"true\" : \"false\");",
+                        tag, expression)
+            }
+            ValueKind::Signed => {
+                format!("printf(\"{}%lld\\n\", (long long) ({}));",
+                        tag, expression)
+            }
+            ValueKind::Unsigned => {
+                format!("printf(\"{}%llu\\n\", (unsigned long long) ({}));",
+                        tag, expression)
+            }
+        }
+    }
+}
+
+// Collect the extra headers needed by a set of queries, without duplicates.
+pub(crate) fn query_headers(queries: &[Query]) -> Vec<&'static str> {
+    let mut headers = vec!["<stdio.h>"];
+    for query in queries {
+        for header in query.headers() {
+            if !headers.contains(&header) {
+                headers.push(header);
+            }
+        }
+    }
+    headers
+}
+
+// The outcome of trying to answer a whole batch of queries with one program.
+// The outer `Err` means that the program itself failed.
+pub(crate) type BatchAttempt = CProbeResult<Vec<CProbeResult<QueryValue>>>;
+
+impl<'a> Probe<'a> {
+    /// Answer a single query.
+    ///
+    /// This is what methods such as `size_of` and `is_signed` use internally.
+    pub fn evaluate(&self, query: &Query) -> CProbeResult<QueryValue> {
+        match self.strategy {
+            EvaluationStrategy::CompileAndRun => {}
+            EvaluationStrategy::CompileOnly => {
+                return self.evaluate_at_compile_time(query);
+            }
+            EvaluationStrategy::ObjectFile => {
+                let queries = std::slice::from_ref(query);
+                return match self.object_file_batch(queries)? {
+                    Ok(mut values) => values.pop().unwrap(),
+                    Err(error) => Err(error),
+                };
+            }
+        }
+        let headers = query_headers(std::slice::from_ref(query));
+        let main_body = format!("{}{}\nreturn 0;",
+                                query.prelude(0),
+                                query.print_statement(0, ""));
+        match query.value_kind() {
+            ValueKind::Bool => {
+                self.run_to_get_rust_constant(headers, &main_body)
+                    .map(QueryValue::Bool)
+            }
+            ValueKind::Signed => {
+                self.run_to_get_rust_constant(headers, &main_body)
+                    .map(QueryValue::Signed)
+            }
+            ValueKind::Unsigned => {
+                self.run_to_get_rust_constant(headers, &main_body)
+                    .map(QueryValue::Unsigned)
+            }
+        }
+    }
+
+    // Typed forms of `evaluate`, for queries whose value kind is known.
+    pub(crate) fn evaluate_bool(&self, query: Query) -> CProbeResult<bool> {
+        self.evaluate(&query)
+            .map(|value| value.as_bool().expect("expected a boolean query"))
+    }
+    pub(crate) fn evaluate_i64(&self, query: Query) -> CProbeResult<i64> {
+        self.evaluate(&query)
+            .map(|value| value.as_i64().expect("expected a signed query"))
+    }
+    pub(crate) fn evaluate_u64(&self, query: Query) -> CProbeResult<u64> {
+        self.evaluate(&query)
+            .map(|value| value.as_u64().expect("expected an unsigned query"))
+    }
+
+    /// Answer many queries with as few probe programs as possible.
+    ///
+    /// All of the queries are first placed in one program, which either prints
+    /// each answer on a line tagged with the query's position, or (with
+    /// `EvaluationStrategy::ObjectFile`) stores each answer in a global
+    /// variable. If that program fails to compile or run, the batch is split in
+    /// half and each half is tried again, so that a bad query only causes an
+    /// error for itself, at a cost of a number of extra programs that is
+    /// logarithmic in the size of the batch.
+    ///
+    /// The results are returned in the same order as the queries. The outer
+    /// `Err` is only used for I/O errors that prevent the batch from being
+    /// processed at all.
+    ///
+    /// With `EvaluationStrategy::CompileOnly`, there is nothing to be gained by
+    /// combining queries, so each one is simply evaluated in turn.
+    pub fn evaluate_batch(&self, queries: &[Query])
+                          -> CProbeResult<Vec<CProbeResult<QueryValue>>> {
+        let mut results = Vec::with_capacity(queries.len());
+        if self.strategy == EvaluationStrategy::CompileOnly {
+            for query in queries {
+                results.push(self.evaluate(query));
+            }
+            return Ok(results);
+        }
+        self.evaluate_batch_into(queries, &mut results)?;
+        Ok(results)
+    }
+
+    fn evaluate_batch_into(&self,
+                           queries: &[Query],
+                           results: &mut Vec<CProbeResult<QueryValue>>)
+                           -> CProbeResult<()> {
+        if queries.is_empty() {
+            return Ok(());
+        }
+        let attempt = match self.strategy {
+            EvaluationStrategy::ObjectFile => self.object_file_batch(queries)?,
+            _ => {
+                if queries.len() == 1 {
+                    results.push(self.evaluate(&queries[0]));
+                    return Ok(());
+                }
+                self.run_batch(queries)?
+            }
+        };
+        match attempt {
+            Ok(values) => {
+                results.extend(values);
+                Ok(())
+            }
+            Err(error) => {
+                if queries.len() == 1 {
+                    results.push(Err(error));
+                    return Ok(());
+                }
+                // Isolate the failure by trying each half separately.
+                let (first, second) = queries.split_at(queries.len() / 2);
+                self.evaluate_batch_into(first, results)?;
+                self.evaluate_batch_into(second, results)
+            }
+        }
+    }
+
+    // Answer a batch of queries with a single program that prints the answers.
+    fn run_batch(&self, queries: &[Query]) -> CProbeResult<BatchAttempt> {
+        let mut main_body = String::new();
+        for (label, query) in queries.iter().enumerate() {
+            main_body.push_str(&query.prelude(label));
+            main_body.push_str(&query.print_statement(label,
+                                                      &format!("{} ", label)));
+            main_body.push('\n');
+        }
+        main_body.push_str("return 0;");
+        let source = self.main_source_template(query_headers(queries),
+                                               &main_body);
+        let compile_run_output = self.check_run(&source)?;
+        let run_out_string =
+            match compile_run_output.successful_run_output_for(&source) {
+                Ok(string) => string,
+                Err(error) => { return Ok(Err(error)); }
+            };
+        let mut answers = vec![None; queries.len()];
+        for line in run_out_string.lines() {
+            let mut words = line.trim().splitn(2, ' ');
+            let label = words.next().and_then(|word| {
+                usize::from_str(word).ok()
+            });
+            if let (Some(label), Some(answer)) = (label, words.next()) {
+                if label < answers.len() {
+                    answers[label] = Some(answer.to_string());
+                }
+            }
+        }
+        Ok(Ok(queries.iter().zip(answers).map(|(query, answer)| {
+            let value = answer.and_then(|answer| {
+                query.value_kind().parse(&answer)
+            });
+            value.ok_or_else(|| {
+                OtherError("unexpected output from probe program".to_string())
+            })
+        }).collect()))
+    }
+}
@@ -0,0 +1,627 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reports of probe results, which can be saved as JSON and read back.
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write as FormatWrite;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{CProbeError, CProbeResult, EvaluationStrategy, Probe};
+use bindings::{error_summary, item_source, BindingDiagnostic, BindingItem,
+               Bindings, ItemValue};
+use functions::FunctionCheck;
+use json::Json;
+use query::{Query, QueryValue};
+
+use self::ReportError::*;
+
+// Identifies the format of report files, in case it ever has to change.
+const REPORT_FORMAT: &str = "probe-c-api report";
+const REPORT_VERSION: &str = "1";
+
+/// Errors that can occur when reading a report.
+#[derive(Debug)]
+pub enum ReportError {
+    /// The report file could not be read.
+    IoError(io::Error),
+    /// The text is not valid JSON, or does not describe a report.
+    ParseError(String),
+}
+
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            IoError(ref error) => {
+                f.write_fmt(format_args!("ReportError: could not read \
+                                          report: {}",
+                                         error))
+            }
+            ParseError(ref message) => {
+                f.write_fmt(format_args!("ReportError: {}", message))
+            }
+        }
+    }
+}
+
+impl Error for ReportError {
+    fn description(&self) -> &str {
+        match *self {
+            IoError(..) => "could not read the report file",
+            ParseError(..) => "the report is not valid",
+        }
+    }
+    fn cause(&self) -> Option<&dyn Error> {
+        match *self {
+            IoError(ref error) => Some(error),
+            ParseError(..) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReportError {
+    fn from(error: io::Error) -> Self {
+        IoError(error)
+    }
+}
+
+/// The settings of the probe that produced a report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToolchainSettings {
+    /// The headers included in every probe program.
+    pub headers: Vec<String>,
+    /// A string identifying the compiler and its settings (see
+    /// `Compiler::identity`), or an empty string if the probe was not
+    /// created from a `Compiler`.
+    pub compiler: String,
+    /// The strategy used to answer queries.
+    pub strategy: EvaluationStrategy,
+}
+
+/// An item in a report, with what was found by probing it.
+#[derive(Debug)]
+pub struct ReportEntry {
+    /// The item that was probed.
+    pub item: BindingItem,
+    /// What was found, or the error that prevented it from being found.
+    pub result: CProbeResult<ItemValue>,
+}
+
+/// A query in a report, with its answer.
+#[derive(Debug)]
+pub struct QueryEntry {
+    /// The query that was evaluated.
+    pub query: Query,
+    /// The answer, or the error that prevented it from being found.
+    pub result: CProbeResult<QueryValue>,
+}
+
+/// A record of items and queries that were probed, their results or errors,
+/// and the settings used to probe them (see `BindingsWriter::report` and
+/// `ProbeReport::evaluate_batch`).
+///
+/// Reports can be written as JSON, e.g. to archive them or compare them
+/// between machines, and read back to generate bindings without probing
+/// again. Errors are only kept as one-line summaries, so a report that is
+/// read back has an `OtherError` with the summary in place of each error.
+#[derive(Debug)]
+pub struct ProbeReport {
+    /// The settings of the probe.
+    pub toolchain: ToolchainSettings,
+    /// The items, in the order they were probed.
+    pub entries: Vec<ReportEntry>,
+    /// The queries, in the order they were evaluated.
+    pub queries: Vec<QueryEntry>,
+}
+
+// Copy an error. I/O errors only keep their kind and message.
+pub(crate) fn copy_error(error: &CProbeError) -> CProbeError {
+    match *error {
+        CProbeError::IoError(ref error) => {
+            CProbeError::IoError(io::Error::new(error.kind(),
+                                                error.to_string()))
+        }
+        CProbeError::CompileError(ref failure) => {
+            CProbeError::CompileError(failure.clone())
+        }
+        CProbeError::RunError(ref compile_output, ref run_output) => {
+            CProbeError::RunError(compile_output.clone(), run_output.clone())
+        }
+        CProbeError::OtherError(ref message) => {
+            CProbeError::OtherError(message.clone())
+        }
+    }
+}
+
+fn json_string(string: &str) -> Json {
+    Json::String(string.to_string())
+}
+
+fn json_strings(strings: &[String]) -> Json {
+    Json::Array(strings.iter().map(|string| json_string(string)).collect())
+}
+
+// The name of each kind of item, as in spec files.
+fn item_kind(item: &BindingItem) -> &'static str {
+    match *item {
+        BindingItem::Typedef(..) => "typedef",
+        BindingItem::Constant(..) => "constant",
+        BindingItem::StringConstant(..) => "string_constant",
+        BindingItem::Enum { .. } => "enum",
+        BindingItem::Struct { .. } => "struct",
+        BindingItem::Function(..) => "function",
+    }
+}
+
+// The name of each kind of query, and its arguments.
+fn query_parts(query: &Query) -> (&'static str, Vec<String>) {
+    match *query {
+        Query::SizeOf(ref type_) => ("size_of", vec![type_.clone()]),
+        Query::AlignOf(ref type_) => ("align_of", vec![type_.clone()]),
+        Query::IsSigned(ref type_) => ("is_signed", vec![type_.clone()]),
+        Query::IsDefinedMacro(ref name) => {
+            ("is_defined_macro", vec![name.clone()])
+        }
+        Query::IsFloating(ref type_) => ("is_floating", vec![type_.clone()]),
+        Query::SignedIntegerConstant(ref expression) => {
+            ("signed_integer_constant", vec![expression.clone()])
+        }
+        Query::UnsignedIntegerConstant(ref expression) => {
+            ("unsigned_integer_constant", vec![expression.clone()])
+        }
+        Query::OffsetOf(ref type_, ref field) => {
+            ("offset_of", vec![type_.clone(), field.clone()])
+        }
+        Query::FieldSizeOf(ref type_, ref field) => {
+            ("field_size_of", vec![type_.clone(), field.clone()])
+        }
+        Query::FieldAlignOf(ref type_, ref field) => {
+            ("field_align_of", vec![type_.clone(), field.clone()])
+        }
+    }
+}
+
+fn query_from_parts(kind: &str, mut arguments: Vec<String>)
+                    -> Option<Query> {
+    let second = if arguments.len() == 2 { arguments.pop() } else { None };
+    let first = arguments.pop()?;
+    if !arguments.is_empty() {
+        return None;
+    }
+    let query = match (kind, second) {
+        ("size_of", None) => Query::SizeOf(first),
+        ("align_of", None) => Query::AlignOf(first),
+        ("is_signed", None) => Query::IsSigned(first),
+        ("is_defined_macro", None) => Query::IsDefinedMacro(first),
+        ("is_floating", None) => Query::IsFloating(first),
+        ("signed_integer_constant", None) => {
+            Query::SignedIntegerConstant(first)
+        }
+        ("unsigned_integer_constant", None) => {
+            Query::UnsignedIntegerConstant(first)
+        }
+        ("offset_of", Some(field)) => Query::OffsetOf(first, field),
+        ("field_size_of", Some(field)) => Query::FieldSizeOf(first, field),
+        ("field_align_of", Some(field)) => Query::FieldAlignOf(first, field),
+        _ => { return None; }
+    };
+    Some(query)
+}
+
+fn query_to_json(entry: &QueryEntry) -> Json {
+    let (kind, arguments) = query_parts(&entry.query);
+    let result = match entry.result {
+        Ok(QueryValue::Bool(value)) => ("bool", Json::Bool(value)),
+        Ok(QueryValue::Signed(value)) => {
+            ("signed", Json::Number(value.to_string()))
+        }
+        Ok(QueryValue::Unsigned(value)) => {
+            ("unsigned", Json::Number(value.to_string()))
+        }
+        Err(ref error) => ("error", json_string(&error_summary(error))),
+    };
+    Json::Object(vec![
+        ("kind".to_string(), json_string(kind)),
+        ("arguments".to_string(), json_strings(&arguments)),
+        (result.0.to_string(), result.1),
+    ])
+}
+
+fn query_from_json(json: &Json, index: usize)
+                   -> Result<QueryEntry, ReportError> {
+    let reader = Reader {
+        json,
+        context: format!("query {}", index),
+    };
+    let kind = reader.string("kind")?;
+    let query = match query_from_parts(&kind, reader.strings("arguments")?) {
+        Some(query) => query,
+        None => {
+            return reader.error(&format!("unknown query `{}` or wrong \
+                                          number of arguments",
+                                         kind));
+        }
+    };
+    let number_error = |key: &str| {
+        reader.error(&format!("`{}` must be an integer", key))
+    };
+    let result = if json.get("error").is_some() {
+        Err(CProbeError::OtherError(reader.string("error")?))
+    } else if json.get("bool").is_some() {
+        Ok(QueryValue::Bool(reader.bool("bool")?))
+    } else if json.get("signed").is_some() {
+        match reader.get("signed")?.as_number() {
+            Some(value) => Ok(QueryValue::Signed(value)),
+            None => { return number_error("signed"); }
+        }
+    } else {
+        match reader.get("unsigned")?.as_number() {
+            Some(value) => Ok(QueryValue::Unsigned(value)),
+            None => { return number_error("unsigned"); }
+        }
+    };
+    Ok(QueryEntry { query, result })
+}
+
+fn entry_to_json(entry: &ReportEntry) -> Json {
+    let mut members = vec![
+        ("kind".to_string(), json_string(item_kind(&entry.item))),
+        ("name".to_string(), json_string(entry.item.c_name())),
+    ];
+    match entry.item {
+        BindingItem::Enum { ref rust_name, ref enumerators, .. } => {
+            members.push(("rust_name".to_string(), json_string(rust_name)));
+            members.push(("enumerators".to_string(),
+                          json_strings(enumerators)));
+        }
+        BindingItem::Struct { ref rust_name, ref fields, .. } => {
+            members.push(("rust_name".to_string(), json_string(rust_name)));
+            members.push(("fields".to_string(), json_strings(fields)));
+        }
+        _ => {}
+    }
+    let mut add = |key: &str, value: Json| {
+        members.push((key.to_string(), value));
+    };
+    match entry.result {
+        Ok(ItemValue::Type(ref rust_type)) => {
+            add("rust_type", json_string(rust_type));
+        }
+        Ok(ItemValue::Constant { ref rust_type, ref value }) => {
+            add("rust_type", json_string(rust_type));
+            add("value", json_string(value));
+        }
+        Ok(ItemValue::Bytes(ref bytes)) => {
+            add("bytes", Json::Array(bytes.iter().map(|byte| {
+                Json::Number(byte.to_string())
+            }).collect()));
+        }
+        Ok(ItemValue::Enum { ref repr, ref values }) => {
+            add("repr", json_string(repr));
+            add("values", Json::Array(values.iter().map(|value| {
+                Json::Number(value.to_string())
+            }).collect()));
+        }
+        Ok(ItemValue::Struct(ref definition)) => {
+            add("definition", json_string(definition));
+        }
+        Ok(ItemValue::Function(check)) => {
+            add("declared", Json::Bool(check.declared));
+            add("links", Json::Bool(check.links));
+        }
+        Err(ref error) => {
+            add("error", json_string(&error_summary(error)));
+        }
+    }
+    Json::Object(members)
+}
+
+// Reads the members of a JSON object, reporting missing or mistyped members
+// in terms of what the object describes.
+struct Reader<'j> {
+    json: &'j Json,
+    context: String,
+}
+
+impl<'j> Reader<'j> {
+    fn error<T>(&self, message: &str) -> Result<T, ReportError> {
+        Err(ParseError(format!("{}: {}", self.context, message)))
+    }
+
+    fn get(&self, key: &str) -> Result<&'j Json, ReportError> {
+        match self.json.get(key) {
+            Some(value) => Ok(value),
+            None => self.error(&format!("missing `{}`", key)),
+        }
+    }
+
+    fn string(&self, key: &str) -> Result<String, ReportError> {
+        match self.get(key)?.as_str() {
+            Some(string) => Ok(string.to_string()),
+            None => self.error(&format!("`{}` must be a string", key)),
+        }
+    }
+
+    fn bool(&self, key: &str) -> Result<bool, ReportError> {
+        match self.get(key)?.as_bool() {
+            Some(value) => Ok(value),
+            None => self.error(&format!("`{}` must be true or false", key)),
+        }
+    }
+
+    fn strings(&self, key: &str) -> Result<Vec<String>, ReportError> {
+        let strings: Option<Vec<String>> = match self.get(key)?.as_array() {
+            Some(values) => values.iter().map(|value| {
+                value.as_str().map(|string| string.to_string())
+            }).collect(),
+            None => None,
+        };
+        match strings {
+            Some(strings) => Ok(strings),
+            None => self.error(&format!("`{}` must be an array of strings",
+                                        key)),
+        }
+    }
+
+    fn numbers<T>(&self, key: &str) -> Result<Vec<T>, ReportError>
+        where T: ::std::str::FromStr {
+        let numbers: Option<Vec<T>> = match self.get(key)?.as_array() {
+            Some(values) => values.iter().map(Json::as_number).collect(),
+            None => None,
+        };
+        match numbers {
+            Some(numbers) => Ok(numbers),
+            None => self.error(&format!("`{}` has an invalid number", key)),
+        }
+    }
+}
+
+fn entry_from_json(json: &Json, index: usize)
+                   -> Result<ReportEntry, ReportError> {
+    let reader = Reader {
+        json,
+        context: format!("entry {}", index),
+    };
+    let name = reader.string("name")?;
+    let kind = reader.string("kind")?;
+    let item = match &kind[..] {
+        "typedef" => BindingItem::Typedef(name),
+        "constant" => BindingItem::Constant(name),
+        "string_constant" => BindingItem::StringConstant(name),
+        "function" => BindingItem::Function(name),
+        "enum" => BindingItem::Enum {
+            enum_type: name,
+            rust_name: reader.string("rust_name")?,
+            enumerators: reader.strings("enumerators")?,
+        },
+        "struct" => BindingItem::Struct {
+            struct_type: name,
+            rust_name: reader.string("rust_name")?,
+            fields: reader.strings("fields")?,
+        },
+        _ => {
+            return reader.error(&format!("unknown item kind `{}`", kind));
+        }
+    };
+    if json.get("error").is_some() {
+        return Ok(ReportEntry {
+            item,
+            result: Err(CProbeError::OtherError(reader.string("error")?)),
+        });
+    }
+    let value = match item {
+        BindingItem::Typedef(..) => {
+            ItemValue::Type(reader.string("rust_type")?)
+        }
+        BindingItem::Constant(..) => ItemValue::Constant {
+            rust_type: reader.string("rust_type")?,
+            value: reader.string("value")?,
+        },
+        BindingItem::StringConstant(..) => {
+            ItemValue::Bytes(reader.numbers("bytes")?)
+        }
+        BindingItem::Enum { ref enumerators, .. } => {
+            let values = reader.numbers("values")?;
+            if values.len() != enumerators.len() {
+                return reader.error("`values` does not match \
+                                     `enumerators`");
+            }
+            ItemValue::Enum {
+                repr: reader.string("repr")?,
+                values,
+            }
+        }
+        BindingItem::Struct { .. } => {
+            ItemValue::Struct(reader.string("definition")?)
+        }
+        BindingItem::Function(..) => ItemValue::Function(FunctionCheck {
+            declared: reader.bool("declared")?,
+            links: reader.bool("links")?,
+        }),
+    };
+    Ok(ReportEntry {
+        item,
+        result: Ok(value),
+    })
+}
+
+impl ProbeReport {
+    /// Create an empty report, recording the settings of `probe`.
+    pub fn new(probe: &Probe) -> ProbeReport {
+        let compiler = match probe.identify {
+            Some(ref identify) => (**identify)(),
+            None => String::new(),
+        };
+        ProbeReport {
+            toolchain: ToolchainSettings {
+                headers: probe.headers.clone(),
+                compiler,
+                strategy: probe.strategy,
+            },
+            entries: Vec::new(),
+            queries: Vec::new(),
+        }
+    }
+
+    /// Answer queries with `probe`, as with `Probe::evaluate_batch`, and
+    /// record each query with its answer or error. If the whole batch fails,
+    /// every query is recorded with that error.
+    pub fn evaluate_batch(&mut self, probe: &Probe, queries: &[Query])
+                          -> Vec<CProbeResult<QueryValue>> {
+        let results = match probe.evaluate_batch(queries) {
+            Ok(results) => results,
+            Err(error) => queries.iter().map(|_| {
+                Err(copy_error(&error))
+            }).collect(),
+        };
+        for (query, result) in queries.iter().zip(&results) {
+            self.queries.push(QueryEntry {
+                query: query.clone(),
+                result: match *result {
+                    Ok(value) => Ok(value),
+                    Err(ref error) => Err(copy_error(error)),
+                },
+            });
+        }
+        results
+    }
+
+    /// Generate bindings from the report, as `BindingsWriter::generate`
+    /// would have. Queries are not included.
+    ///
+    /// An entry whose value is not of the kind that its item produces, e.g. a
+    /// `Bytes` value for a `Typedef`, is treated as an `OtherError`.
+    pub fn bindings(&self) -> Bindings {
+        let mut source = "// Generated by probe-c-api. Do not edit.\n"
+                         .to_string();
+        let mut diagnostics = Vec::new();
+        for entry in &self.entries {
+            source.push('\n');
+            let item_source = match entry.result {
+                Ok(ref value) => {
+                    item_source(&entry.item, value).ok_or_else(|| {
+                        CProbeError::OtherError("the value does not match \
+                                                 the kind of item"
+                                                    .to_string())
+                    })
+                }
+                Err(ref error) => Err(copy_error(error)),
+            };
+            match item_source {
+                Ok(item_source) => { source.push_str(&item_source); }
+                Err(error) => {
+                    let diagnostic = BindingDiagnostic {
+                        c_name: entry.item.c_name().to_string(),
+                        kind: entry.item.description(),
+                        error,
+                    };
+                    writeln!(&mut source, "// {}", diagnostic).unwrap();
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+        Bindings {
+            source,
+            diagnostics,
+        }
+    }
+
+    /// Write the report as JSON.
+    ///
+    /// Each item and setting is written on its own line, so that reports
+    /// from different machines can be compared with an ordinary text diff.
+    pub fn to_json(&self) -> String {
+        let toolchain = &self.toolchain;
+        let json = Json::Object(vec![
+            ("format".to_string(), json_string(REPORT_FORMAT)),
+            ("version".to_string(), Json::Number(REPORT_VERSION.to_string())),
+            ("toolchain".to_string(), Json::Object(vec![
+                ("headers".to_string(), json_strings(&toolchain.headers)),
+                ("compiler".to_string(), json_string(&toolchain.compiler)),
+                ("strategy".to_string(),
+                 json_string(toolchain.strategy.name())),
+            ])),
+            ("entries".to_string(),
+             Json::Array(self.entries.iter().map(entry_to_json).collect())),
+            ("queries".to_string(),
+             Json::Array(self.queries.iter().map(query_to_json).collect())),
+        ]);
+        let mut text = String::new();
+        json.write(&mut text, 0);
+        text.push('\n');
+        text
+    }
+
+    /// Read a report written by `to_json`.
+    pub fn from_json(text: &str) -> Result<ProbeReport, ReportError> {
+        let json = Json::parse(text).map_err(ParseError)?;
+        let reader = Reader {
+            json: &json,
+            context: "report".to_string(),
+        };
+        if reader.string("format")? != REPORT_FORMAT {
+            return reader.error("not a probe-c-api report");
+        }
+        let version = reader.get("version")?;
+        if *version != Json::Number(REPORT_VERSION.to_string()) {
+            return reader.error("unsupported report version");
+        }
+        let toolchain = Reader {
+            json: reader.get("toolchain")?,
+            context: "toolchain".to_string(),
+        };
+        let strategy = toolchain.string("strategy")?;
+        let strategy = match EvaluationStrategy::from_name(&strategy) {
+            Some(strategy) => strategy,
+            None => {
+                return toolchain.error(&format!("unknown strategy `{}`",
+                                                strategy));
+            }
+        };
+        let entries = match reader.get("entries")?.as_array() {
+            Some(entries) => entries,
+            None => { return reader.error("`entries` must be an array"); }
+        };
+        let queries = match reader.get("queries")?.as_array() {
+            Some(queries) => queries,
+            None => { return reader.error("`queries` must be an array"); }
+        };
+        Ok(ProbeReport {
+            toolchain: ToolchainSettings {
+                headers: toolchain.strings("headers")?,
+                compiler: toolchain.string("compiler")?,
+                strategy,
+            },
+            entries: entries.iter().enumerate().map(|(index, entry)| {
+                entry_from_json(entry, index)
+            }).collect::<Result<_, _>>()?,
+            queries: queries.iter().enumerate().map(|(index, query)| {
+                query_from_json(query, index)
+            }).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Write the report to a file, as JSON.
+    pub fn write_to_file(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    /// Read a report from a file written by `write_to_file`.
+    pub fn read_from_file(path: &Path) -> Result<ProbeReport, ReportError> {
+        ProbeReport::from_json(&fs::read_to_string(path)?)
+    }
+}
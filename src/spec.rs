@@ -0,0 +1,805 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Declarative API spec files, listing the settings and items to probe.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{self, Path, PathBuf};
+
+use super::{EvaluationStrategy, NewProbeError, Probe};
+use bindings::{BindingItem, Bindings, BindingsWriter};
+use builder::ProbeBuilder;
+use layout::rust_identifier;
+use pkg_config::{PkgConfig, PkgConfigError};
+use report::ProbeReport;
+
+use self::SpecError::*;
+
+/// Errors that can occur when loading or running an API spec.
+#[derive(Debug)]
+pub enum SpecError {
+    /// The spec file could not be read.
+    IoError(io::Error),
+    /// The spec is not valid, either because it is not in the supported
+    /// subset of TOML, or because of a missing, unknown or mistyped setting.
+    ParseError {
+        /// The line where the problem was found, starting at 1.
+        line: usize,
+        /// A description of the problem.
+        message: String,
+    },
+    /// One of the packages listed in `pkg_config` could not be found.
+    PkgConfigFailed(PkgConfigError),
+    /// The settings were read, but the probe could not be created.
+    ProbeFailed(NewProbeError),
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            IoError(ref error) => {
+                f.write_fmt(format_args!("SpecError: could not read spec: {}",
+                                         error))
+            }
+            ParseError { line, ref message } => {
+                f.write_fmt(format_args!("SpecError: line {}: {}",
+                                         line, message))
+            }
+            PkgConfigFailed(ref error) => {
+                f.write_fmt(format_args!("SpecError: {}", error))
+            }
+            ProbeFailed(ref error) => {
+                f.write_fmt(format_args!("SpecError: {}", error))
+            }
+        }
+    }
+}
+
+impl Error for SpecError {
+    fn description(&self) -> &str {
+        match *self {
+            IoError(..) => "could not read the spec file",
+            ParseError { .. } => "the spec is not valid",
+            PkgConfigFailed(..) => "could not get settings from pkg-config",
+            ProbeFailed(..) => "could not create a probe",
+        }
+    }
+    fn cause(&self) -> Option<&dyn Error> {
+        match *self {
+            IoError(ref error) => Some(error),
+            ParseError { .. } => None,
+            PkgConfigFailed(ref error) => Some(error),
+            ProbeFailed(ref error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for SpecError {
+    fn from(error: io::Error) -> Self {
+        IoError(error)
+    }
+}
+
+fn parse_error<T>(line: usize, message: String) -> Result<T, SpecError> {
+    Err(ParseError { line, message })
+}
+
+// A value in the subset of TOML that spec files use.
+#[derive(Clone, Debug)]
+enum Value {
+    String(String),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+// A table of settings, with the line where each key was set.
+#[derive(Debug)]
+struct Table {
+    line: usize,
+    entries: Vec<(String, Value, usize)>,
+}
+
+impl Table {
+    fn new(line: usize) -> Table {
+        Table {
+            line,
+            entries: Vec::new(),
+        }
+    }
+
+    fn take(&mut self, key: &str) -> Option<(Value, usize)> {
+        let index = self.entries.iter().position(|entry| entry.0 == key)?;
+        let (_, value, line) = self.entries.remove(index);
+        Some((value, line))
+    }
+
+    fn take_string(&mut self, key: &str) -> Result<Option<String>, SpecError> {
+        match self.take(key) {
+            None => Ok(None),
+            Some((Value::String(string), _)) => Ok(Some(string)),
+            Some((_, line)) => {
+                parse_error(line, format!("`{}` must be a string", key))
+            }
+        }
+    }
+
+    fn take_required_string(&mut self, key: &str)
+                            -> Result<String, SpecError> {
+        match self.take_string(key)? {
+            Some(string) => Ok(string),
+            None => parse_error(self.line, format!("missing `{}`", key)),
+        }
+    }
+
+    fn take_bool(&mut self, key: &str) -> Result<Option<bool>, SpecError> {
+        match self.take(key) {
+            None => Ok(None),
+            Some((Value::Bool(value), _)) => Ok(Some(value)),
+            Some((_, line)) => {
+                parse_error(line, format!("`{}` must be true or false", key))
+            }
+        }
+    }
+
+    fn take_strings(&mut self, key: &str) -> Result<Vec<String>, SpecError> {
+        let (values, line) = match self.take(key) {
+            None => { return Ok(Vec::new()); }
+            Some((Value::Array(values), line)) => (values, line),
+            Some((_, line)) => {
+                return parse_error(line, format!("`{}` must be an array of \
+                                                  strings",
+                                                 key));
+            }
+        };
+        values.into_iter().map(|value| {
+            match value {
+                Value::String(string) => Ok(string),
+                _ => parse_error(line, format!("`{}` must be an array of \
+                                                strings",
+                                               key)),
+            }
+        }).collect()
+    }
+
+    // Complain about any key that wasn't taken.
+    fn finish(self) -> Result<(), SpecError> {
+        match self.entries.into_iter().next() {
+            Some((key, _, line)) => {
+                parse_error(line, format!("unknown setting `{}`", key))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+// Where the settings that follow a table header go.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Header {
+    Root,
+    Table(String),
+    ArrayTable(String),
+}
+
+// Parser for the subset of TOML used by spec files (see `ApiSpec`).
+struct Parser<'t> {
+    chars: ::std::iter::Peekable<::std::str::Chars<'t>>,
+    line: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn error<T>(&self, message: &str) -> Result<T, SpecError> {
+        parse_error(self.line, message.to_string())
+    }
+
+    // Report valid TOML that spec files don't accept.
+    fn unsupported<T>(&self, what: &str) -> Result<T, SpecError> {
+        self.error(&format!("{} are not supported in spec files (see \
+                             `ApiSpec` for the accepted subset of TOML)",
+                            what))
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c != ' ' && c != '\t' {
+                break;
+            }
+            self.chars.next();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.chars.peek() == Some(&'#') {
+            while let Some(&c) = self.chars.peek() {
+                if c == '\n' {
+                    break;
+                }
+                self.chars.next();
+            }
+        }
+    }
+
+    // Skip any blank lines and comments, e.g. between array elements.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.chars.peek() {
+                Some(&'\n') => {
+                    self.chars.next();
+                    self.line += 1;
+                }
+                Some(&'\r') => {
+                    self.chars.next();
+                }
+                _ => { return; }
+            }
+        }
+    }
+
+    // Check that nothing but a comment follows on the current line.
+    fn end_of_line(&mut self) -> Result<(), SpecError> {
+        self.skip_spaces();
+        self.skip_comment();
+        if self.chars.peek() == Some(&'\r') {
+            self.chars.next();
+        }
+        match self.chars.next() {
+            None => Ok(()),
+            Some('\n') => {
+                self.line += 1;
+                Ok(())
+            }
+            Some(c) => self.error(&format!("unexpected `{}`", c)),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SpecError> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => {
+                self.error(&format!("expected `{}`, found `{}`", expected, c))
+            }
+            None => self.error(&format!("expected `{}`", expected)),
+        }
+    }
+
+    fn key(&mut self) -> Result<String, SpecError> {
+        self.skip_spaces();
+        let key = match self.chars.peek() {
+            Some(&'"') => self.basic_string()?,
+            Some(&'\'') => self.literal_string()?,
+            _ => {
+                let mut key = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        break;
+                    }
+                    key.push(c);
+                    self.chars.next();
+                }
+                if key.is_empty() {
+                    return self.error("expected a key");
+                }
+                key
+            }
+        };
+        self.skip_spaces();
+        if self.chars.peek() == Some(&'.') {
+            return self.unsupported("dotted keys");
+        }
+        Ok(key)
+    }
+
+    // After an opening quote, check for a third quote, which would start a
+    // multi-line string. Returns whether the string is empty.
+    fn empty_or_multi_line(&mut self, quote: char) -> Result<bool, SpecError> {
+        if self.chars.peek() != Some(&quote) {
+            return Ok(false);
+        }
+        self.chars.next();
+        if self.chars.peek() == Some(&quote) {
+            return self.unsupported("multi-line strings");
+        }
+        Ok(true)
+    }
+
+    fn basic_string(&mut self) -> Result<String, SpecError> {
+        self.expect('"')?;
+        let mut string = String::new();
+        if self.empty_or_multi_line('"')? {
+            return Ok(string);
+        }
+        loop {
+            match self.chars.next() {
+                Some('"') => { return Ok(string); }
+                Some('\\') => {
+                    let escaped = match self.chars.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('u') => {
+                            let digits: String =
+                                self.chars.by_ref().take(4).collect();
+                            match u32::from_str_radix(&digits, 16).ok()
+                                      .and_then(::std::char::from_u32) {
+                                Some(c) => c,
+                                None => {
+                                    return self.error("bad unicode escape");
+                                }
+                            }
+                        }
+                        _ => { return self.error("bad escape in string"); }
+                    };
+                    string.push(escaped);
+                }
+                Some('\n') | None => {
+                    return self.error("unterminated string");
+                }
+                Some(c) => { string.push(c); }
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, SpecError> {
+        self.expect('\'')?;
+        let mut string = String::new();
+        if self.empty_or_multi_line('\'')? {
+            return Ok(string);
+        }
+        loop {
+            match self.chars.next() {
+                Some('\'') => { return Ok(string); }
+                Some('\n') | None => {
+                    return self.error("unterminated string");
+                }
+                Some(c) => { string.push(c); }
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, SpecError> {
+        match self.chars.peek() {
+            Some(&'"') => self.basic_string().map(Value::String),
+            Some(&'\'') => self.literal_string().map(Value::String),
+            Some(&'[') => {
+                self.chars.next();
+                let mut values = Vec::new();
+                loop {
+                    self.skip_blank();
+                    if self.chars.peek() == Some(&']') {
+                        self.chars.next();
+                        return Ok(Value::Array(values));
+                    }
+                    values.push(self.value()?);
+                    self.skip_blank();
+                    match self.chars.next() {
+                        Some(',') => {}
+                        Some(']') => { return Ok(Value::Array(values)); }
+                        _ => {
+                            return self.error("expected `,` or `]` in array");
+                        }
+                    }
+                }
+            }
+            Some(&'{') => self.unsupported("inline tables"),
+            Some(&c) if c.is_ascii_digit() || c == '+' || c == '-' => {
+                self.unsupported("numbers and dates")
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    word.push(c);
+                    self.chars.next();
+                }
+                match &word[..] {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "inf" | "nan" => self.unsupported("numbers and dates"),
+                    _ => self.error("expected a string, boolean or array"),
+                }
+            }
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<(Header, Table)>, SpecError> {
+        let mut tables = vec![(Header::Root, Table::new(1))];
+        loop {
+            self.skip_blank();
+            match self.chars.peek() {
+                None => { return Ok(tables); }
+                Some(&'[') => {
+                    self.chars.next();
+                    let is_array = self.chars.peek() == Some(&'[');
+                    if is_array {
+                        self.chars.next();
+                    }
+                    let name = self.key()?;
+                    self.skip_spaces();
+                    self.expect(']')?;
+                    let header = if is_array {
+                        self.expect(']')?;
+                        Header::ArrayTable(name)
+                    } else {
+                        Header::Table(name)
+                    };
+                    if let Header::Table(..) = header {
+                        if tables.iter().any(|table| table.0 == header) {
+                            return self.error("table defined twice");
+                        }
+                    }
+                    tables.push((header, Table::new(self.line)));
+                }
+                Some(..) => {
+                    let key = self.key()?;
+                    self.skip_spaces();
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let line = self.line;
+                    let value = self.value()?;
+                    let table = &mut tables.last_mut().unwrap().1;
+                    if table.entries.iter().any(|entry| entry.0 == key) {
+                        return parse_error(line, format!("`{}` is set twice",
+                                                         key));
+                    }
+                    table.entries.push((key, value, line));
+                }
+            }
+            self.end_of_line()?;
+        }
+    }
+}
+
+// Compiler settings from the `[compiler]` table.
+#[derive(Clone, Debug, Default)]
+struct CompilerSettings {
+    program: Option<PathBuf>,
+    clang: bool,
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<String>,
+    libraries: Vec<String>,
+    library_dirs: Vec<PathBuf>,
+    std: Option<String>,
+    flags: Vec<String>,
+    link_flags: Vec<String>,
+    pkg_config: Vec<String>,
+    run_env: Vec<(String, String)>,
+    strategy: Option<EvaluationStrategy>,
+    work_dir: Option<PathBuf>,
+}
+
+/// A list of headers, compiler settings and C items to probe, usually read
+/// from a spec file.
+///
+/// Spec files are written in a subset of TOML, which accepts exactly:
+///
+/// * `#` comments, on their own lines or after a value;
+/// * bare keys (letters, digits, `_` and `-`) and quoted keys, but not
+///   dotted keys;
+/// * single-line basic (`"..."`) and literal (`'...'`) strings, where basic
+///   strings may use the escapes `\"`, `\\`, `\n`, `\t`, `\r` and `\uXXXX`;
+/// * `true` and `false`;
+/// * arrays of the above, which may span several lines and contain comments
+///   and a trailing comma;
+/// * `[table]` and `[[table]]` (array of tables) headers, with undotted
+///   names.
+///
+/// Numbers, dates, multi-line strings, inline tables and dotted keys are
+/// reported as errors, as are tables defined twice. For example:
+///
+/// ```toml
+/// headers = ["<sys/stat.h>", "\"mylib.h\""]
+///
+/// [compiler]
+/// include_dirs = ["include"]
+/// defines = ["_GNU_SOURCE", "MYLIB_API=2"]
+/// libraries = ["mylib"]
+///
+/// [[item]]
+/// kind = "typedef"
+/// name = "mode_t"
+///
+/// [[item]]
+/// kind = "struct"
+/// name = "struct stat"
+/// rust_name = "Stat"
+/// fields = ["st_mode", "st_size"]
+/// ```
+///
+/// The `[compiler]` table may contain `program` (the compiler command, which
+/// is looked up on `PATH` unless it contains a path separator),
+/// `clang` (true to use Clang's defaults instead of GCC's), `include_dirs`,
+/// `defines` (each either `NAME` or `NAME=VALUE`), `libraries`,
+/// `library_dirs`, `std`, `flags`, `link_flags` (used only when linking),
+/// `pkg_config` (packages whose settings are added, see `PkgConfig`),
+/// `run_env` (each `NAME=VALUE`), `strategy` (one of `compile-and-run`,
+/// `compile-only` or `object-file`) and `work_dir`.
+/// Relative paths, including a `program` with a path separator, are taken
+/// relative to the directory containing the spec file.
+///
+/// Each `[[item]]` has a `kind` and a C `name`. The kinds are `typedef`,
+/// `constant`, `string_constant`, `function`, `enum` (which also takes a list
+/// of `enumerators`) and `struct` (which also takes a list of `fields`).
+/// Enums and structs may set a `rust_name`; otherwise, the C name is used,
+/// without the `enum` or `struct` keyword. Items are bound as described for
+/// `BindingsWriter`.
+///
+/// Unknown tables and settings are reported as errors, so that typos are not
+/// silently ignored.
+#[derive(Clone, Debug)]
+pub struct ApiSpec {
+    headers: Vec<String>,
+    compiler: CompilerSettings,
+    items: Vec<BindingItem>,
+}
+
+// Interpret one `[[item]]` table.
+fn parse_item(mut table: Table) -> Result<BindingItem, SpecError> {
+    let kind = table.take_required_string("kind")?;
+    let name = table.take_required_string("name")?;
+    let default_rust_name = |keyword: &str| {
+        rust_identifier(name.strip_prefix(keyword).unwrap_or(&name).trim())
+    };
+    let item = match &kind[..] {
+        "typedef" => BindingItem::Typedef(name),
+        "constant" => BindingItem::Constant(name),
+        "string_constant" => BindingItem::StringConstant(name),
+        "function" => BindingItem::Function(name),
+        "enum" => {
+            let rust_name = match table.take_string("rust_name")? {
+                Some(rust_name) => rust_name,
+                None => default_rust_name("enum "),
+            };
+            BindingItem::Enum {
+                enumerators: table.take_strings("enumerators")?,
+                enum_type: name,
+                rust_name,
+            }
+        }
+        "struct" => {
+            let rust_name = match table.take_string("rust_name")? {
+                Some(rust_name) => rust_name,
+                None => default_rust_name("struct "),
+            };
+            BindingItem::Struct {
+                fields: table.take_strings("fields")?,
+                struct_type: name,
+                rust_name,
+            }
+        }
+        _ => {
+            return parse_error(table.line, format!("unknown item kind `{}`",
+                                                   kind));
+        }
+    };
+    table.finish()?;
+    Ok(item)
+}
+
+// Interpret the `[compiler]` table, resolving paths against `base_dir`.
+fn parse_compiler(mut table: Table, base_dir: &Path)
+                  -> Result<CompilerSettings, SpecError> {
+    let path = |path: String| base_dir.join(path);
+    // A bare command name, such as `clang`, is found on `PATH`.
+    let program = |program: String| {
+        if program.chars().any(path::is_separator) {
+            base_dir.join(program)
+        } else {
+            PathBuf::from(program)
+        }
+    };
+    let strategy = match table.take("strategy") {
+        None => None,
+        Some((Value::String(ref strategy), line)) => {
+            match EvaluationStrategy::from_name(strategy) {
+                Some(strategy) => Some(strategy),
+                None => {
+                    return parse_error(line, format!("unknown strategy `{}`",
+                                                     strategy));
+                }
+            }
+        }
+        Some((_, line)) => {
+            return parse_error(line, "`strategy` must be a string"
+                                     .to_string());
+        }
+    };
+    let run_env_line = table.entries.iter()
+                                    .find(|entry| entry.0 == "run_env")
+                                    .map_or(table.line, |entry| entry.2);
+    let mut run_env = Vec::new();
+    for variable in table.take_strings("run_env")? {
+        let mut parts = variable.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => {
+                run_env.push((name.to_string(), value.to_string()));
+            }
+            _ => {
+                return parse_error(run_env_line,
+                                   format!("`{}` is not of the form \
+                                            NAME=VALUE",
+                                           variable));
+            }
+        }
+    }
+    let settings = CompilerSettings {
+        program: table.take_string("program")?.map(program),
+        clang: table.take_bool("clang")?.unwrap_or(false),
+        include_dirs: table.take_strings("include_dirs")?.into_iter()
+                           .map(&path).collect(),
+        defines: table.take_strings("defines")?,
+        libraries: table.take_strings("libraries")?,
+        library_dirs: table.take_strings("library_dirs")?.into_iter()
+                           .map(&path).collect(),
+        std: table.take_string("std")?,
+        flags: table.take_strings("flags")?,
+        link_flags: table.take_strings("link_flags")?,
+        pkg_config: table.take_strings("pkg_config")?,
+        run_env,
+        strategy,
+        work_dir: table.take_string("work_dir")?.map(&path),
+    };
+    table.finish()?;
+    Ok(settings)
+}
+
+impl ApiSpec {
+    /// Parse a spec. Relative paths are taken relative to the current
+    /// directory.
+    pub fn parse(text: &str) -> Result<ApiSpec, SpecError> {
+        ApiSpec::parse_in_dir(text, Path::new(""))
+    }
+
+    /// Read and parse a spec file.
+    pub fn from_file(path: &Path) -> Result<ApiSpec, SpecError> {
+        let text = fs::read_to_string(path)?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        ApiSpec::parse_in_dir(&text, base_dir)
+    }
+
+    fn parse_in_dir(text: &str, base_dir: &Path)
+                    -> Result<ApiSpec, SpecError> {
+        let parser = Parser {
+            chars: text.chars().peekable(),
+            line: 1,
+        };
+        let mut spec = ApiSpec {
+            headers: Vec::new(),
+            compiler: CompilerSettings::default(),
+            items: Vec::new(),
+        };
+        for (header, mut table) in parser.parse()? {
+            match header {
+                Header::Root => {
+                    spec.headers = table.take_strings("headers")?;
+                    table.finish()?;
+                }
+                Header::Table(ref name) if name == "compiler" => {
+                    spec.compiler = parse_compiler(table, base_dir)?;
+                }
+                Header::ArrayTable(ref name) if name == "item" => {
+                    spec.items.push(parse_item(table)?);
+                }
+                Header::Table(name) | Header::ArrayTable(name) => {
+                    return parse_error(table.line,
+                                       format!("unknown table `{}`", name));
+                }
+            }
+        }
+        Ok(spec)
+    }
+
+    /// The headers to include in every probe program.
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// The items to probe, in the order they were listed.
+    pub fn items(&self) -> &[BindingItem] {
+        &self.items
+    }
+
+    /// Get a builder with the spec's headers and compiler settings, to which
+    /// more settings can be added. This runs `pkg-config` for any packages
+    /// listed in the spec.
+    pub fn probe_builder(&self) -> Result<ProbeBuilder, SpecError> {
+        let settings = &self.compiler;
+        let mut builder = Probe::builder();
+        for package in &settings.pkg_config {
+            let library = PkgConfig::new(package).find()
+                                                 .map_err(PkgConfigFailed)?;
+            builder = builder.pkg_config(&library);
+        }
+        for header in &self.headers {
+            builder = builder.header(header);
+        }
+        if let Some(ref work_dir) = settings.work_dir {
+            builder = builder.work_dir(work_dir);
+        }
+        if settings.clang {
+            builder = builder.clang();
+        }
+        if let Some(ref program) = settings.program {
+            builder = builder.compiler_program(program);
+        }
+        for dir in &settings.include_dirs {
+            builder = builder.include_dir(dir);
+        }
+        for define in &settings.defines {
+            let mut parts = define.splitn(2, '=');
+            let name = parts.next().unwrap();
+            builder = match parts.next() {
+                Some(value) => builder.define_value(name, value),
+                None => builder.define(name),
+            };
+        }
+        for library in &settings.libraries {
+            builder = builder.library(library);
+        }
+        for dir in &settings.library_dirs {
+            builder = builder.library_dir(dir);
+        }
+        if let Some(ref std) = settings.std {
+            builder = builder.std(std);
+        }
+        for flag in &settings.flags {
+            builder = builder.flag(flag);
+        }
+        for flag in &settings.link_flags {
+            builder = builder.link_flag(flag);
+        }
+        for (name, value) in &settings.run_env {
+            builder = builder.run_env(name, value);
+        }
+        if let Some(strategy) = settings.strategy {
+            builder = builder.evaluation_strategy(strategy);
+        }
+        Ok(builder)
+    }
+
+    /// Create a probe with the spec's headers and compiler settings.
+    pub fn probe(&self) -> Result<Probe<'static>, SpecError> {
+        self.probe_builder()?.build().map_err(ProbeFailed)
+    }
+
+    /// Get a writer that binds the spec's items using `probe`.
+    pub fn bindings_writer<'p, 'a>(&self, probe: &'p Probe<'a>)
+                                   -> BindingsWriter<'p, 'a> {
+        let mut writer = BindingsWriter::new(probe);
+        for item in &self.items {
+            writer.item(item.clone());
+        }
+        writer
+    }
+
+    /// Create a probe from the spec, and use it to probe the spec's items,
+    /// recording the results in a report.
+    pub fn report(&self) -> Result<ProbeReport, SpecError> {
+        let probe = self.probe()?;
+        Ok(self.bindings_writer(&probe).report())
+    }
+
+    /// Create a probe from the spec, and use it to generate bindings for the
+    /// spec's items.
+    pub fn generate(&self) -> Result<Bindings, SpecError> {
+        Ok(self.report()?.bindings())
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Probing of string constants.
+
+use super::{CProbeResult, Probe};
+use super::CProbeError::*;
+
+// Decode a string of hexadecimal digit pairs.
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let digits = text.as_bytes();
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    digits.chunks(2).map(|pair| {
+        let high = (pair[0] as char).to_digit(16)?;
+        let low = (pair[1] as char).to_digit(16)?;
+        Some((high * 16 + low) as u8)
+    }).collect()
+}
+
+impl<'a> Probe<'a> {
+    // Print `length` bytes of a string as hexadecimal digits, then decode
+    // them. Since each byte becomes two digits, nothing in the string can be
+    // confused with the program's formatting.
+    fn string_bytes(&self, string: &str, length: &str)
+                    -> CProbeResult<Vec<u8>> {
+        let source = self.main_source_template(
+            vec!["<stdio.h>", "<string.h>"],
+            &format!("const char *probe_c_api_string = ({});\n\
+                      size_t probe_c_api_length = (size_t) ({});\n\
+                      size_t i;\n\
+                      for (i = 0; i < probe_c_api_length; ++i) {{\n\
+                      printf(\"%02x\", (unsigned) \
+                             (unsigned char) probe_c_api_string[i]);\n\
+                      }}\n\
+                      return 0;",
+                     string, length),
+        );
+        let compile_run_output = self.check_run(&source)?;
+        let run_out_string =
+            compile_run_output.successful_run_output_for(&source)?;
+        match decode_hex(&run_out_string) {
+            Some(bytes) => Ok(bytes),
+            None => Err(OtherError("unexpected output from probe program"
+                                   .to_string())),
+        }
+    }
+
+    /// Get the exact bytes of a NUL-terminated string constant, such as a
+    /// string macro or a `const char[]` global variable.
+    ///
+    /// The terminating NUL is not included. Any other bytes, including
+    /// newlines, quotes and bytes that are not valid UTF-8, are returned
+    /// unchanged. Unlike the integer constant methods, no whitespace is
+    /// trimmed.
+    ///
+    /// This always runs a probe program, regardless of the probe's
+    /// `EvaluationStrategy`.
+    pub fn string_constant(&self, constant: &str) -> CProbeResult<Vec<u8>> {
+        self.string_bytes(constant, "strlen(probe_c_api_string)")
+    }
+
+    /// Get the exact bytes of a string constant of known length, which may
+    /// contain embedded NUL bytes.
+    ///
+    /// The `length` is a C expression giving the number of bytes to read. For
+    /// a string literal or array, `sizeof(constant) - 1` gives the length
+    /// without the terminating NUL.
+    pub fn string_constant_with_length(&self, constant: &str, length: &str)
+                                       -> CProbeResult<Vec<u8>> {
+        self.string_bytes(constant, length)
+    }
+}
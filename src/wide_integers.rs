@@ -0,0 +1,120 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Probing of integer constants of any width.
+
+use super::{CProbeResult, Probe};
+use super::CProbeError::*;
+
+// Bits printed on each line of output.
+const CHUNK_BITS: u32 = 16;
+
+impl<'a> Probe<'a> {
+    // Get the sign and magnitude of an integer constant. The magnitude is
+    // printed in 16-bit chunks, least significant first, so that the program
+    // never needs an integer type at least as wide as the constant. Negative
+    // values are printed as their complement, since right shifts of negative
+    // values are implementation-defined.
+    //
+    // The returned value is the complement of the constant if it is negative.
+    fn wide_integer_constant(&self, constant: &str)
+                             -> CProbeResult<(bool, u128)> {
+        let source = self.main_source_template(
+            vec!["<limits.h>", "<stdio.h>"],
+            &format!("int probe_c_api_negative = ({0}) < 0;\n\
+                      unsigned probe_c_api_bits = \
+                          (unsigned) (sizeof({0}) * CHAR_BIT);\n\
+                      unsigned i;\n\
+                      printf(\"%d %u\\n\", probe_c_api_negative, \
+                             probe_c_api_bits);\n\
+                      for (i = 0; i < probe_c_api_bits; i += {1}) {{\n\
+                      printf(\"%x\\n\", (unsigned) (probe_c_api_negative\n\
+                                 ? (~({0}) >> i) & 0xffff\n\
+                                 : (({0}) >> i) & 0xffff));\n\
+                      }}\n\
+                      return 0;",
+                     constant, CHUNK_BITS),
+        );
+        let compile_run_output = self.check_run(&source)?;
+        let run_out_string =
+            compile_run_output.successful_run_output_for(&source)?;
+        let bad_output = || OtherError("unexpected output from probe program"
+                                       .to_string());
+        let mut lines = run_out_string.lines();
+        let mut header = lines.next().ok_or_else(bad_output)?.split(' ');
+        let negative = match header.next() {
+            Some("0") => false,
+            Some("1") => true,
+            _ => { return Err(bad_output()); }
+        };
+        let bits: u32 = header.next().and_then(|bits| bits.parse().ok())
+                              .ok_or_else(bad_output)?;
+        let mut value: u128 = 0;
+        let mut chunk_count = 0;
+        for (index, line) in lines.enumerate() {
+            let chunk = u128::from_str_radix(line, 16)
+                            .map_err(|_| bad_output())?;
+            let shift = index as u32 * CHUNK_BITS;
+            if shift >= u128::BITS {
+                if chunk != 0 {
+                    return Err(OtherError(format!("{} does not fit in 128 \
+                                                   bits",
+                                                  constant)));
+                }
+            } else {
+                value |= chunk << shift;
+            }
+            chunk_count += 1;
+        }
+        if chunk_count != (bits + CHUNK_BITS - 1) / CHUNK_BITS {
+            return Err(bad_output());
+        }
+        Ok((negative, value))
+    }
+
+    /// Get the value of a signed integer constant of any width.
+    ///
+    /// This works like `signed_integer_constant`, but handles constants of
+    /// types wider than 64 bits, such as GCC's `__int128`. The probe program
+    /// prints the value in pieces, so it does not need a type as wide as the
+    /// constant in order to print it. An `OtherError` is returned if the value
+    /// does not fit in an `i128`.
+    ///
+    /// This always runs a probe program, regardless of the probe's
+    /// `EvaluationStrategy`.
+    pub fn wide_signed_integer_constant(&self, constant: &str)
+                                        -> CProbeResult<i128> {
+        let (negative, value) = self.wide_integer_constant(constant)?;
+        if value > i128::MAX as u128 {
+            return Err(OtherError(format!("{} does not fit in an i128",
+                                          constant)));
+        }
+        let value = value as i128;
+        Ok(if negative { !value } else { value })
+    }
+
+    /// Get the value of an unsigned integer constant of any width.
+    ///
+    /// See the documentation for `wide_signed_integer_constant` for details.
+    /// An `OtherError` is returned if the value is negative or does not fit in
+    /// a `u128`.
+    pub fn wide_unsigned_integer_constant(&self, constant: &str)
+                                          -> CProbeResult<u128> {
+        let (negative, value) = self.wide_integer_constant(constant)?;
+        if negative {
+            return Err(OtherError(format!("{} is negative", constant)));
+        }
+        Ok(value)
+    }
+}
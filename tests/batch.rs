@@ -0,0 +1,78 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::process::Command;
+
+use probe_c_api::{CProbeError, Probe, Query, QueryValue};
+
+fn new_constant_probe() -> Probe<'static> {
+    Probe::new(
+        vec!["\"tests/test_constants.h\"".into()],
+        &env::temp_dir(),
+        |source_path, exe_path| {
+            Command::new("gcc").arg(source_path)
+                               .arg(format!("-I{}", env!("CARGO_MANIFEST_DIR")))
+                               .arg("-o").arg(exe_path)
+                               .output()
+        },
+        |exe_path| {
+            Command::new(exe_path).output()
+        },
+    ).unwrap()
+}
+
+#[test]
+fn batch_of_mixed_queries() {
+    let probe = new_constant_probe();
+    let results = probe.evaluate_batch(&[
+        Query::SizeOf("char".into()),
+        Query::IsSigned("unsigned int".into()),
+        Query::IsDefinedMacro("NEGATIVE_ONE".into()),
+        Query::SignedIntegerConstant("negative_one".into()),
+        Query::UnsignedIntegerConstant("huge_64_bit_int".into()),
+    ]).unwrap();
+    let values: Vec<QueryValue> =
+        results.into_iter().map(|result| result.unwrap()).collect();
+    assert_eq!(vec![QueryValue::Unsigned(1),
+                    QueryValue::Bool(false),
+                    QueryValue::Bool(true),
+                    QueryValue::Signed(-1),
+                    QueryValue::Unsigned(u64::MAX)],
+               values);
+}
+
+#[test]
+fn batch_isolates_bad_query() {
+    let probe = new_constant_probe();
+    let results = probe.evaluate_batch(&[
+        Query::SizeOf("char".into()),
+        Query::SignedIntegerConstant("NEGATIVE_ONE".into()),
+        Query::SizeOf("struct not_a_real_struct".into()),
+        Query::UnsignedIntegerConstant("ONE".into()),
+    ]).unwrap();
+    assert_eq!(4, results.len());
+    assert_eq!(Some(1), results[0].as_ref().unwrap().as_u64());
+    assert_eq!(Some(-1), results[1].as_ref().unwrap().as_i64());
+    assert!(matches!(results[2], Err(CProbeError::CompileError(..))));
+    assert_eq!(Some(1), results[3].as_ref().unwrap().as_u64());
+}
+
+#[test]
+fn empty_batch() {
+    let probe = new_constant_probe();
+    assert!(probe.evaluate_batch(&[]).unwrap().is_empty());
+}
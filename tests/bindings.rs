@@ -0,0 +1,154 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use probe_c_api::{BindingsWriter, Probe};
+
+fn new_bindings_probe() -> Probe<'static> {
+    Probe::builder().header("\"tests/test_types.h\"")
+                    .header("\"tests/test_constants.h\"")
+                    .header("\"tests/test_enums.h\"")
+                    .header("\"tests/test_structs.h\"")
+                    .include_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+                    .build()
+                    .unwrap()
+}
+
+#[test]
+fn bindings_for_each_kind_of_item() {
+    let probe = new_bindings_probe();
+    let mut writer = BindingsWriter::new(&probe);
+    writer.typedef("alias_signed_32_bit")
+          .typedef("alias_float_64_bit")
+          .constant("NEGATIVE_ONE")
+          .constant("huge_64_bit_int")
+          .constant("FLOAT_THIRD")
+          .string_constant("VERSION_STRING")
+          .enumeration("enum status", "status",
+                       &["STATUS_ERROR", "STATUS_OK", "STATUS_PENDING"])
+          .structure("struct inner", "Inner", &["x", "y"]);
+    let bindings = writer.generate();
+    assert!(bindings.diagnostics.is_empty(), "{:?}", bindings.diagnostics);
+    let expected = "\
+// Generated by probe-c-api. Do not edit.
+
+/// C type `alias_signed_32_bit`.
+pub type alias_signed_32_bit = i32;
+
+/// C type `alias_float_64_bit`.
+pub type alias_float_64_bit = f64;
+
+/// C constant `NEGATIVE_ONE`.
+pub const NEGATIVE_ONE: i32 = -1;
+
+/// C constant `huge_64_bit_int`.
+pub const huge_64_bit_int: u64 = 18446744073709551615;
+
+/// C constant `FLOAT_THIRD`.
+pub const FLOAT_THIRD: f32 = 0.33333334;
+
+/// C constant `VERSION_STRING`.
+pub const VERSION_STRING: &[u8] = b\"1.2.3\";
+
+/// C type `enum status`.
+pub type status = i32;
+/// C enumerator `STATUS_ERROR`.
+pub const STATUS_ERROR: status = -1;
+/// C enumerator `STATUS_OK`.
+pub const STATUS_OK: status = 0;
+/// C enumerator `STATUS_PENDING`.
+pub const STATUS_PENDING: status = 5;
+
+/// C type `struct inner`.
+#[repr(C)]
+pub struct Inner {
+    pub x: i16,
+    pub _padding0: [u8; 6],
+    pub y: i64,
+}
+";
+    assert_eq!(expected, bindings.source);
+}
+
+#[test]
+fn bindings_diagnostics() {
+    let probe = new_bindings_probe();
+    let mut writer = BindingsWriter::new(&probe);
+    writer.typedef("not_a_type")
+          .constant("NOT_A_CONSTANT")
+          .constant("VERSION_STRING")
+          .constant("ONE");
+    let bindings = writer.generate();
+    assert_eq!(3, bindings.diagnostics.len());
+    assert_eq!("not_a_type", bindings.diagnostics[0].c_name);
+    assert_eq!("type", bindings.diagnostics[0].kind);
+    assert_eq!("NOT_A_CONSTANT", bindings.diagnostics[1].c_name);
+    assert_eq!("VERSION_STRING", bindings.diagnostics[2].c_name);
+    let message = bindings.diagnostics[1].to_string();
+    assert!(message.starts_with("could not bind constant `NOT_A_CONSTANT`: \
+                                 compilation failed"),
+            "{}", message);
+    assert!(bindings.source.contains(&format!("// {}\n", message)));
+    assert!(bindings.source.contains("pub const ONE: i32 = 1;\n"));
+}
+
+#[test]
+fn bindings_probed_in_one_batch() {
+    let compiles = Arc::new(AtomicUsize::new(0));
+    let counter = compiles.clone();
+    let probe = Probe::new(vec!["\"tests/test_types.h\"".into(),
+                                "\"tests/test_constants.h\"".into()],
+                           &env::temp_dir(),
+                           move |source_path, exe_path| {
+                               counter.fetch_add(1, Ordering::SeqCst);
+                               Command::new("gcc")
+                                   .arg("-I").arg(env!("CARGO_MANIFEST_DIR"))
+                                   .arg(source_path)
+                                   .arg("-o").arg(exe_path)
+                                   .output()
+                           },
+                           |exe_path| Command::new(exe_path).output())
+                      .unwrap();
+    let mut writer = BindingsWriter::new(&probe);
+    writer.typedef("alias_signed_32_bit")
+          .typedef("alias_float_64_bit")
+          .constant("NEGATIVE_ONE")
+          .constant("huge_64_bit_int")
+          .constant("ONE");
+    let bindings = writer.generate();
+    assert!(bindings.diagnostics.is_empty(), "{:?}", bindings.diagnostics);
+    assert!(bindings.source.contains("pub const NEGATIVE_ONE: i32 = -1;\n"));
+    assert_eq!(1, compiles.load(Ordering::SeqCst));
+}
+
+#[test]
+fn bindings_written_to_file() {
+    let probe = new_bindings_probe();
+    let path = env::temp_dir().join(format!("probe-c-api-bindings-{}.rs",
+                                            process::id()));
+    let mut writer = BindingsWriter::new(&probe);
+    writer.typedef("alias_unsigned_8_bit");
+    assert!(writer.write_to_file(&path).unwrap().is_empty());
+    let source = fs::read_to_string(&path).unwrap();
+    assert!(source.ends_with("pub type alias_unsigned_8_bit = u8;\n"));
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,95 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::path::Path;
+
+use probe_c_api::{EvaluationStrategy, Probe};
+
+#[test]
+fn builder_defaults() {
+    let probe = Probe::builder().build().unwrap();
+    assert_eq!(4, probe.size_of("int").unwrap());
+    assert_eq!(EvaluationStrategy::CompileAndRun, probe.evaluation_strategy());
+    assert!(probe.has_preprocess());
+}
+
+#[test]
+fn builder_headers_and_include_dirs() {
+    let probe = Probe::builder().header("\"tests/test_types.h\"")
+                                .include_dir(Path::new(
+                                    env!("CARGO_MANIFEST_DIR")))
+                                .work_dir(&env::temp_dir())
+                                .std("c11")
+                                .flag("-Wall")
+                                .build()
+                                .unwrap();
+    assert_eq!(Some("f64".to_string()),
+               probe.equivalent_rust_float("alias_float_64_bit").unwrap());
+}
+
+#[test]
+fn builder_defines_come_before_headers() {
+    let probe = Probe::builder().define("_GNU_SOURCE")
+                                .define_value("PROBE_TEST_VALUE", "7")
+                                .header("<sched.h>")
+                                .build()
+                                .unwrap();
+    assert!(probe.is_defined_macro("CPU_SETSIZE").unwrap());
+    assert_eq!(7, probe.signed_integer_constant("PROBE_TEST_VALUE").unwrap());
+}
+
+#[test]
+fn builder_libraries() {
+    let probe = Probe::builder().header("<math.h>")
+                                .library("m")
+                                .library_dir(Path::new("/usr/lib"))
+                                .build()
+                                .unwrap();
+    assert!(probe.has_function("cos").unwrap().links);
+}
+
+#[test]
+fn builder_run_env() {
+    let probe = Probe::builder().run_env("PROBE_TEST_VARIABLE", "hello")
+                                .build()
+                                .unwrap();
+    let output = probe.check_run("#include <stdio.h>\n\
+                                  #include <stdlib.h>\n\
+                                  int main(void) {\n\
+                                  fputs(getenv(\"PROBE_TEST_VARIABLE\"), \
+                                        stdout);\n\
+                                  return 0;\n\
+                                  }\n").unwrap();
+    assert_eq!("hello", output.successful_run_output().unwrap());
+}
+
+#[test]
+fn builder_evaluation_strategy() {
+    let probe = Probe::builder()
+                      .evaluation_strategy(EvaluationStrategy::CompileOnly)
+                      .build()
+                      .unwrap();
+    assert_eq!(EvaluationStrategy::CompileOnly, probe.evaluation_strategy());
+    assert_eq!(4, probe.size_of("int").unwrap());
+}
+
+#[test]
+fn builder_missing_work_dir() {
+    assert!(Probe::builder().work_dir(Path::new("/probe-c-api/no/such/dir"))
+                            .build()
+                            .is_err());
+}
@@ -0,0 +1,32 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::default::Default;
+
+use probe_c_api::CTypeKind::*;
+use probe_c_api::Probe;
+
+#[test]
+fn classify_c_types() {
+    let probe = <Probe>::default();
+    assert!(matches!(probe.c_type("double").unwrap().kind, CFloat));
+    assert!(matches!(probe.c_type("_Bool").unwrap().kind,
+                     CInteger{ signed: false }));
+    let int_type = probe.c_type("int").unwrap();
+    assert!(matches!(int_type.kind, CInteger{ signed: true }));
+    assert_eq!(probe.size_of("int").unwrap(), int_type.size);
+    assert!(matches!(probe.c_type("char *").unwrap().kind, CUnknown));
+}
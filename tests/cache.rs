@@ -0,0 +1,173 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use probe_c_api::{PreprocessMode, Probe};
+
+// A fresh directory for each test, holding a header and the cache.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("probe-c-api-cache-test-{}-{}",
+                                           process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("cached.h"), "#define CACHED_VALUE 1\n").unwrap();
+    dir
+}
+
+// A gcc probe that counts compilations, and optionally has a preprocess
+// command.
+fn counting_probe(dir: &Path, compiles: Arc<AtomicU32>, preprocess: bool)
+                  -> Probe<'static> {
+    let include = format!("-I{}", dir.display());
+    let preprocess_include = include.clone();
+    let mut probe = Probe::new(
+        vec!["\"cached.h\"".into()],
+        &env::temp_dir(),
+        move |source_path, exe_path| {
+            compiles.fetch_add(1, Ordering::SeqCst);
+            Command::new("gcc").arg(source_path)
+                               .arg(&include)
+                               .arg("-o").arg(exe_path)
+                               .output()
+        },
+        |exe_path| {
+            Command::new(exe_path).output()
+        },
+    ).unwrap();
+    if preprocess {
+        probe.set_preprocess(move |source_path, mode| {
+            let mut command = Command::new("gcc");
+            if mode == PreprocessMode::DefinedMacros {
+                command.arg("-dM");
+            }
+            command.arg("-E").arg(&preprocess_include).arg(source_path)
+                   .output()
+        });
+    }
+    probe
+}
+
+#[test]
+fn cache_hits_skip_compilation() {
+    let dir = test_dir("hits");
+    let compiles = Arc::new(AtomicU32::new(0));
+    let mut probe = counting_probe(&dir, compiles.clone(), true);
+    probe.enable_cache_in(&dir.join("cache")).unwrap();
+    assert_eq!(1, probe.signed_integer_constant("CACHED_VALUE").unwrap());
+    assert_eq!(1, compiles.load(Ordering::SeqCst));
+    assert_eq!(1, probe.signed_integer_constant("CACHED_VALUE").unwrap());
+    assert_eq!(4, probe.size_of("int").unwrap());
+    assert!(!probe.check_compile("not C").unwrap().status.success());
+    assert!(!probe.check_compile("not C").unwrap().status.success());
+    assert_eq!(3, compiles.load(Ordering::SeqCst));
+    // The cache persists for other probes.
+    let mut other_probe = counting_probe(&dir, compiles.clone(), true);
+    other_probe.enable_cache_in(&dir.join("cache")).unwrap();
+    assert_eq!(1, other_probe.signed_integer_constant("CACHED_VALUE")
+                             .unwrap());
+    assert_eq!(3, compiles.load(Ordering::SeqCst));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cache_invalidated_by_header_change() {
+    let dir = test_dir("headers");
+    let compiles = Arc::new(AtomicU32::new(0));
+    let mut probe = counting_probe(&dir, compiles.clone(), true);
+    probe.enable_cache_in(&dir.join("cache")).unwrap();
+    assert_eq!(1, probe.signed_integer_constant("CACHED_VALUE").unwrap());
+    fs::write(dir.join("cached.h"), "#define CACHED_VALUE 2\n").unwrap();
+    assert_eq!(2, probe.signed_integer_constant("CACHED_VALUE").unwrap());
+    assert_eq!(2, compiles.load(Ordering::SeqCst));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cache_keyed_by_compiler_identity() {
+    let dir = test_dir("identity");
+    let compiles = Arc::new(AtomicU32::new(0));
+    let mut probe = counting_probe(&dir, compiles.clone(), true);
+    probe.enable_cache_in(&dir.join("cache")).unwrap();
+    assert_eq!(4, probe.size_of("int").unwrap());
+    // A new identity applies without enabling the cache again.
+    probe.set_compiler_identity("another compiler");
+    assert_eq!(4, probe.size_of("int").unwrap());
+    assert_eq!(2, compiles.load(Ordering::SeqCst));
+    assert_eq!(4, probe.size_of("int").unwrap());
+    assert_eq!(2, compiles.load(Ordering::SeqCst));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// The only file in a cache directory.
+fn only_entry(cache_dir: &Path) -> PathBuf {
+    let mut entries: Vec<PathBuf> = fs::read_dir(cache_dir).unwrap()
+                                       .map(|entry| entry.unwrap().path())
+                                       .collect();
+    assert_eq!(1, entries.len());
+    entries.pop().unwrap()
+}
+
+#[test]
+fn cache_ignores_mismatched_or_truncated_entries() {
+    let dir = test_dir("mismatch");
+    let compiles = Arc::new(AtomicU32::new(0));
+    let mut probe = counting_probe(&dir, compiles.clone(), true);
+    probe.enable_cache_in(&dir.join("int")).unwrap();
+    assert_eq!(4, probe.size_of("int").unwrap());
+    probe.enable_cache_in(&dir.join("char")).unwrap();
+    assert_eq!(1, probe.size_of("char").unwrap());
+    assert_eq!(2, compiles.load(Ordering::SeqCst));
+    // Put the entry for one request where the other's belongs, as a hash
+    // collision would.
+    let char_entry = only_entry(&dir.join("char"));
+    fs::copy(only_entry(&dir.join("int")), &char_entry).unwrap();
+    assert_eq!(1, probe.size_of("char").unwrap());
+    assert_eq!(3, compiles.load(Ordering::SeqCst));
+    assert_eq!(1, probe.size_of("char").unwrap());
+    assert_eq!(3, compiles.load(Ordering::SeqCst));
+    // A truncated entry is recomputed too.
+    let bytes = fs::read(&char_entry).unwrap();
+    fs::write(&char_entry, &bytes[..bytes.len() - 1]).unwrap();
+    assert_eq!(1, probe.size_of("char").unwrap());
+    assert_eq!(4, compiles.load(Ordering::SeqCst));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cache_requires_preprocess() {
+    let dir = test_dir("no-preprocess");
+    let compiles = Arc::new(AtomicU32::new(0));
+    let mut probe = counting_probe(&dir, compiles.clone(), false);
+    assert!(probe.enable_cache_in(&dir.join("cache")).is_err());
+    assert!(!dir.join("cache").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cache_with_compiler() {
+    let dir = test_dir("compiler");
+    let mut probe = Probe::builder().work_dir(&dir).build().unwrap();
+    probe.enable_cache().unwrap();
+    assert_eq!(8, probe.size_of("long long").unwrap());
+    assert_eq!(8, probe.size_of("long long").unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}
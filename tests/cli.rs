@@ -0,0 +1,105 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::process::{Command, Output};
+
+// Run the command-line tool, with the test headers available.
+fn probe_c_api(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_probe-c-api"))
+            .arg("-I")
+            .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests"))
+            .args(args)
+            .output()
+            .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn cli_size_and_alignment() {
+    let output = probe_c_api(&["-H", "\"test_types.h\"",
+                               "size-of", "alias_signed_32_bit"]);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("4\n", stdout(&output));
+    let output = probe_c_api(&["-H\"test_types.h\"", "--json",
+                               "align-of", "alias_unsigned_16_bit"]);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("{\"command\": \"align-of\", \
+                \"argument\": \"alias_unsigned_16_bit\", \"value\": 2}\n",
+               stdout(&output));
+}
+
+#[test]
+fn cli_constants() {
+    let output = probe_c_api(&["-H", "\"test_constants.h\"",
+                               "constant", "NEGATIVE_ONE"]);
+    assert_eq!("-1\n", stdout(&output));
+    let output = probe_c_api(&["-DPROBE_TEST_VALUE=0x10", "--json",
+                               "constant", "PROBE_TEST_VALUE * 2"]);
+    assert!(stdout(&output).ends_with("\"value\": 32}\n"));
+    let output = probe_c_api(&["-H", "\"test_constants.h\"",
+                               "constant", "FLOAT_THIRD * 3"]);
+    assert_eq!("1.0\n", stdout(&output));
+    // Arguments that look like options must follow `--`.
+    let output = probe_c_api(&["constant", "--", "-1"]);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("-1\n", stdout(&output));
+    let output = probe_c_api(&["constant", "-1"]);
+    assert_eq!(Some(2), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`--`"));
+}
+
+#[test]
+fn cli_yes_or_no() {
+    let output = probe_c_api(&["-H", "stdio.h", "is-defined", "EOF"]);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("yes\n", stdout(&output));
+    let output = probe_c_api(&["is-defined", "PROBE_C_API_NOT_DEFINED"]);
+    assert_eq!(Some(1), output.status.code());
+    assert_eq!("no\n", stdout(&output));
+    let output = probe_c_api(&["-H", "math.h", "-l", "m", "--json",
+                               "has-function", "cos"]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(stdout(&output).ends_with("\"value\": {\"declared\": true, \
+                                       \"links\": true}}\n"));
+}
+
+#[test]
+fn cli_run_spec() {
+    let output = probe_c_api(&["run-spec",
+                               concat!(env!("CARGO_MANIFEST_DIR"),
+                                       "/tests/test_api.toml")]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(stdout(&output).contains("pub type alias_unsigned_16_bit = u16;"));
+}
+
+#[test]
+fn cli_failures() {
+    let output = probe_c_api(&["size-of", "struct nonexistent"]);
+    assert_eq!(Some(2), output.status.code());
+    assert!(output.stdout.is_empty());
+    let output = probe_c_api(&["--json", "size-of", "struct nonexistent"]);
+    assert_eq!(Some(2), output.status.code());
+    assert!(stdout(&output).contains("\"error\": \"compilation error"));
+    let output = probe_c_api(&["--json", "size-of", "struct \"quoted\"\t"]);
+    assert!(stdout(&output).contains(
+        "\"argument\": \"struct \\\"quoted\\\"\\t\""));
+    let output = probe_c_api(&["--frobnicate", "size-of", "int"]);
+    assert_eq!(Some(2), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--frobnicate"));
+    let output = probe_c_api(&["size-of"]);
+    assert_eq!(Some(2), output.status.code());
+}
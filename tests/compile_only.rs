@@ -0,0 +1,62 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::path::Path;
+
+use probe_c_api::{CProbeError, EvaluationStrategy, Probe};
+
+fn new_compile_only_probe() -> Probe<'static> {
+    Probe::builder().header("\"tests/test_constants.h\"")
+                    .include_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+                    .evaluation_strategy(EvaluationStrategy::CompileOnly)
+                    .build()
+                    .unwrap()
+}
+
+#[test]
+fn compile_only_size_and_alignment() {
+    let probe = new_compile_only_probe();
+    assert_eq!(1, probe.size_of("char").unwrap());
+    assert_eq!(8, probe.size_of("int64_t").unwrap());
+    assert_eq!(probe.size_of("int32_t").unwrap(),
+               probe.align_of("int32_t").unwrap());
+}
+
+#[test]
+fn compile_only_sign_and_macros() {
+    let probe = new_compile_only_probe();
+    assert!(probe.is_signed("int").unwrap());
+    assert!(!probe.is_signed("unsigned int").unwrap());
+    assert!(probe.is_defined_macro("NEGATIVE_ONE").unwrap());
+    assert!(!probe.is_defined_macro("THISSHOULDNTBEDEFINED").unwrap());
+}
+
+#[test]
+fn compile_only_integer_constants() {
+    let probe = new_compile_only_probe();
+    assert_eq!(-1, probe.signed_integer_constant("NEGATIVE_ONE").unwrap());
+    assert_eq!(i64::MIN, probe.signed_integer_constant("INT64_MIN").unwrap());
+    assert_eq!(1, probe.unsigned_integer_constant("ONE").unwrap());
+    assert_eq!(u64::MAX,
+               probe.unsigned_integer_constant("UINT64_MAX").unwrap());
+}
+
+#[test]
+fn compile_only_requires_constant_expression() {
+    let probe = new_compile_only_probe();
+    let error = probe.signed_integer_constant("negative_one").unwrap_err();
+    assert!(matches!(error, CProbeError::CompileError(..)));
+}
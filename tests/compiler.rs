@@ -0,0 +1,89 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use probe_c_api::{CompilerOptions, EvaluationStrategy, GccCompatible,
+                  Probe};
+
+fn test_options() -> CompilerOptions {
+    CompilerOptions {
+        include_dirs: vec![PathBuf::from(env!("CARGO_MANIFEST_DIR"))],
+        defines: vec![("PROBE_TEST_FLAG".to_string(), None),
+                      ("PROBE_TEST_VALUE".to_string(), Some("42".to_string()))],
+        libraries: vec!["m".to_string()],
+        std: Some("c11".to_string()),
+        ..CompilerOptions::default()
+    }
+}
+
+#[test]
+fn gcc_with_options() {
+    let probe = Probe::with_compiler(vec!["\"tests/test_types.h\"".into()],
+                                     &env::temp_dir(),
+                                     GccCompatible::gcc(test_options()))
+                      .unwrap();
+    assert_eq!(4, probe.size_of("alias_signed_32_bit").unwrap());
+    assert!(probe.is_defined_macro("PROBE_TEST_FLAG").unwrap());
+    assert_eq!(42, probe.signed_integer_constant("PROBE_TEST_VALUE").unwrap());
+    assert!(probe.has_preprocess());
+}
+
+#[test]
+fn gcc_links_libraries() {
+    let probe = Probe::with_compiler(vec!["<math.h>".into()],
+                                     &env::temp_dir(),
+                                     GccCompatible::gcc(test_options()))
+                      .unwrap();
+    assert!(probe.has_function("cos").unwrap().links);
+}
+
+#[test]
+fn gcc_object_file() {
+    let mut probe = Probe::with_compiler(vec!["\"tests/test_types.h\"".into()],
+                                         &env::temp_dir(),
+                                         GccCompatible::gcc(test_options()))
+                          .unwrap();
+    probe.set_evaluation_strategy(EvaluationStrategy::ObjectFile);
+    assert_eq!(8, probe.size_of("alias_unsigned_64_bit").unwrap());
+}
+
+#[test]
+fn gcc_missing_program() {
+    let compiler = GccCompatible::new(
+        Path::new("probe-c-api-no-such-compiler"),
+        CompilerOptions::default(),
+    );
+    let probe = Probe::with_compiler(vec![], &env::temp_dir(), compiler)
+                      .unwrap();
+    assert!(probe.size_of("int").is_err());
+}
+
+#[test]
+fn clang_with_options() {
+    // Clang is not installed everywhere that GCC is.
+    if Command::new("clang").arg("--version").output().is_err() {
+        return;
+    }
+    let probe = Probe::with_compiler(vec!["\"tests/test_types.h\"".into()],
+                                     &env::temp_dir(),
+                                     GccCompatible::clang(test_options()))
+                      .unwrap();
+    assert_eq!(2, probe.size_of("alias_unsigned_16_bit").unwrap());
+    assert!(probe.is_defined_macro("PROBE_TEST_FLAG").unwrap());
+}
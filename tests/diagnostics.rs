@@ -0,0 +1,94 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use probe_c_api::{parse_diagnostics, CProbeError, CompileErrorKind, Probe,
+                   Severity};
+
+#[test]
+fn parse_gcc_diagnostics() {
+    let source = "#include <stdio.h>\nint main(void) {\nreturn x;\n}\n";
+    let stderr = "\
+/tmp/source-123.c: In function 'main':
+/tmp/source-123.c:3:8: error: 'x' undeclared (first use in this function)
+    3 | return x;
+      |        ^
+/tmp/source-123.c:3:8: note: each undeclared identifier is reported only once
+/tmp/other.h:7: warning: this is a warning
+collect2: error: ld returned 1 exit status
+";
+    let diagnostics = parse_diagnostics(stderr, source);
+    assert_eq!(4, diagnostics.len());
+    assert_eq!("/tmp/source-123.c", diagnostics[0].file);
+    assert_eq!(Some(3), diagnostics[0].line);
+    assert_eq!(Some(8), diagnostics[0].column);
+    assert_eq!(Severity::Error, diagnostics[0].severity);
+    assert_eq!("'x' undeclared (first use in this function)",
+               diagnostics[0].message);
+    assert_eq!(Some("return x;"), diagnostics[0].source_line.as_deref());
+    assert_eq!(Severity::Note, diagnostics[1].severity);
+    assert_eq!((Some(7), None), (diagnostics[2].line, diagnostics[2].column));
+    assert_eq!(Severity::Warning, diagnostics[2].severity);
+    assert_eq!(None, diagnostics[2].source_line);
+    assert_eq!("collect2", diagnostics[3].file);
+    assert_eq!(None, diagnostics[3].line);
+}
+
+#[test]
+fn undeclared_identifier() {
+    let probe = Probe::builder().build().unwrap();
+    let error = probe.size_of("probe_c_api_undeclared").unwrap_err();
+    match error {
+        CProbeError::CompileError(ref failure) => {
+            assert_eq!(CompileErrorKind::UndeclaredIdentifier(
+                           "probe_c_api_undeclared".into()),
+                       failure.kind());
+            let first_error = failure.first_error().unwrap();
+            assert!(first_error.source_line.as_ref().unwrap()
+                               .contains("sizeof(probe_c_api_undeclared)"));
+        }
+        _ => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn unknown_type_name() {
+    let probe = Probe::builder().build().unwrap();
+    let error = probe.object_representation("probe_c_api_unknown_t", "0")
+                     .unwrap_err();
+    assert_eq!(CompileErrorKind::UnknownTypeName(
+                   "probe_c_api_unknown_t".into()),
+               error.compile_failure().unwrap().kind());
+}
+
+#[test]
+fn incomplete_type() {
+    let probe = Probe::builder().build().unwrap();
+    let error = probe.size_of("struct probe_c_api_incomplete").unwrap_err();
+    assert!(matches!(error, CProbeError::CompileError(..)));
+    assert_eq!(CompileErrorKind::IncompleteType(
+                   "struct probe_c_api_incomplete".into()),
+               error.compile_failure().unwrap().kind());
+}
+
+#[test]
+fn unclassified_compile_error() {
+    let probe = Probe::builder().build().unwrap();
+    let error = probe.size_of("><").unwrap_err();
+    assert!(matches!(error, CProbeError::CompileError(..)));
+    let failure = error.compile_failure().unwrap();
+    assert!(failure.first_error().is_some());
+    assert_eq!(CompileErrorKind::Other, failure.kind());
+}
@@ -0,0 +1,83 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::process::Command;
+
+use probe_c_api::{CProbeError, Probe};
+
+fn new_enum_probe() -> Probe<'static> {
+    Probe::new(
+        vec!["\"tests/test_enums.h\"".into()],
+        &env::temp_dir(),
+        |source_path, exe_path| {
+            Command::new("gcc").arg(source_path)
+                               .arg(format!("-I{}", env!("CARGO_MANIFEST_DIR")))
+                               .arg("-o").arg(exe_path)
+                               .output()
+        },
+        |exe_path| {
+            Command::new(exe_path).output()
+        },
+    ).unwrap()
+}
+
+#[test]
+fn contiguous_enum() {
+    let probe = new_enum_probe();
+    let info = probe.enum_info("enum color", &["RED", "GREEN", "BLUE"])
+                    .unwrap();
+    assert_eq!(vec![("RED".to_string(), 0),
+                    ("GREEN".to_string(), 1),
+                    ("BLUE".to_string(), 2)],
+               info.enumerators);
+    assert_eq!(probe.size_of("enum color").unwrap(), info.size);
+    assert_eq!(Some(format!("u{}", 8 * info.size)), info.repr);
+    assert!(info.duplicates.is_empty());
+    assert!(info.contiguous);
+}
+
+#[test]
+fn enum_with_duplicates_and_gaps() {
+    let probe = new_enum_probe();
+    let info = probe.enum_info("enum status",
+                               &["STATUS_ERROR", "STATUS_OK",
+                                 "STATUS_SUCCESS", "STATUS_PENDING"])
+                    .unwrap();
+    assert!(info.signed);
+    assert_eq!(vec![vec!["STATUS_OK".to_string(),
+                         "STATUS_SUCCESS".to_string()]],
+               info.duplicates);
+    assert!(!info.contiguous);
+}
+
+#[test]
+fn enum_with_unsigned_values() {
+    let probe = new_enum_probe();
+    let info = probe.enum_info("enum large_unsigned", &["LARGE_UNSIGNED"])
+                    .unwrap();
+    assert!(!info.signed);
+    assert_eq!(vec![("LARGE_UNSIGNED".to_string(), 0xffff_ffff)],
+               info.enumerators);
+    assert_eq!(Some("u32".to_string()), info.repr);
+}
+
+#[test]
+fn enum_value_out_of_range() {
+    let probe = new_enum_probe();
+    assert!(matches!(probe.enum_info("enum too_large", &["TOO_LARGE"]),
+                     Err(CProbeError::OtherError(..))));
+}
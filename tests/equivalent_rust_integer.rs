@@ -45,3 +45,27 @@ fn check_equivalent_rust_integer() {
                                                                .unwrap());
     }
 }
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn check_equivalent_rust_integer_128_bit() {
+    let probe = Probe::new(
+        vec!["\"tests/test_types.h\"".into()],
+        &env::temp_dir(),
+        |source_path, exe_path| {
+            Command::new("gcc").arg(source_path)
+                               .arg(format!("-I{}", env!("CARGO_MANIFEST_DIR")))
+                               .arg("-o").arg(exe_path)
+                               .output()
+        },
+        |exe_path| {
+            Command::new(exe_path).output()
+        },
+    ).unwrap();
+    assert_eq!("i128",
+               probe.equivalent_rust_integer("alias_signed_128_bit")
+                    .unwrap().unwrap());
+    assert_eq!("u128",
+               probe.equivalent_rust_integer("alias_unsigned_128_bit")
+                    .unwrap().unwrap());
+}
@@ -0,0 +1,59 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::path::Path;
+
+use probe_c_api::{CProbeError, Probe};
+
+fn new_probe_with_headers(headers: Vec<String>) -> Probe<'static> {
+    let mut builder = Probe::builder();
+    for header in &headers {
+        builder = builder.header(header);
+    }
+    builder.include_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+           .build()
+           .unwrap()
+}
+
+#[test]
+fn header_existence() {
+    let probe = new_probe_with_headers(vec![]);
+    assert!(probe.has_header("<stdio.h>").unwrap());
+    assert!(!probe.has_header("<probe_c_api_no_such_header.h>").unwrap());
+}
+
+#[test]
+fn type_and_member_existence() {
+    let probe = new_probe_with_headers(vec!["\"tests/test_structs.h\"".into(),
+                                            "<errno.h>".into()]);
+    assert!(probe.has_type("struct outer").unwrap());
+    assert!(!probe.has_type("errno").unwrap());
+    assert!(probe.has_type("padded_tail").unwrap());
+    assert!(!probe.has_type("struct probe_c_api_no_such_struct").unwrap());
+    assert!(!probe.has_type("1").unwrap());
+    assert!(!probe.has_type("sizeof(struct outer)").unwrap());
+    assert!(probe.has_member("struct outer", "inner.y").unwrap());
+    assert!(!probe.has_member("struct outer", "nonexistent").unwrap());
+}
+
+#[test]
+fn broken_probe_is_an_error() {
+    let probe = new_probe_with_headers(
+        vec!["<probe_c_api_no_such_header.h>".into()]
+    );
+    assert!(matches!(probe.has_type("int"),
+                     Err(CProbeError::CompileError(..))));
+}
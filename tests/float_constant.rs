@@ -0,0 +1,78 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::process::Command;
+
+use probe_c_api::{CFloatType, EvaluationStrategy, Probe};
+
+fn new_float_probe() -> Probe<'static> {
+    Probe::new(
+        vec!["\"tests/test_constants.h\"".into(),
+             "<float.h>".into(),
+             "<math.h>".into()],
+        &env::temp_dir(),
+        |source_path, exe_path| {
+            Command::new("gcc").arg(source_path)
+                               .arg(format!("-I{}", env!("CARGO_MANIFEST_DIR")))
+                               .arg("-o").arg(exe_path)
+                               .output()
+        },
+        |exe_path| {
+            Command::new(exe_path).output()
+        },
+    ).unwrap()
+}
+
+#[test]
+fn exact_float_constants() {
+    let probe = new_float_probe();
+    let epsilon = probe.double_constant("DBL_EPSILON").unwrap();
+    assert_eq!(f64::EPSILON, epsilon.value);
+    assert_eq!(CFloatType::Double, epsilon.c_type);
+    let tenth = probe.double_constant("double_tenth").unwrap();
+    assert_eq!(0.1, tenth.value);
+    let third = probe.float_constant("FLOAT_THIRD").unwrap();
+    assert_eq!(1.0f32 / 3.0, third.value);
+    assert_eq!(CFloatType::Float, third.c_type);
+    let half = probe.double_constant("LONG_DOUBLE_HALF").unwrap();
+    assert_eq!(0.5, half.value);
+    assert_eq!(CFloatType::LongDouble, half.c_type);
+}
+
+#[test]
+fn extreme_float_constants() {
+    let probe = new_float_probe();
+    assert_eq!(f64::MAX, probe.double_constant("DBL_MAX").unwrap().value);
+    assert_eq!(f64::MIN_POSITIVE,
+               probe.double_constant("DBL_MIN").unwrap().value);
+    assert_eq!(f64::from_bits(1),
+               probe.double_constant("DBL_MIN * DBL_EPSILON").unwrap().value);
+    assert_eq!(f64::NEG_INFINITY,
+               probe.double_constant("-INFINITY").unwrap().value);
+    assert!(probe.double_constant("NAN").unwrap().value.is_nan());
+    assert_eq!(CFloatType::Other, probe.double_constant("1").unwrap().c_type);
+}
+
+#[test]
+fn float_constant_from_object_file() {
+    let mut probe = new_float_probe();
+    probe.set_evaluation_strategy(EvaluationStrategy::ObjectFile);
+    let epsilon = probe.double_constant("DBL_EPSILON").unwrap();
+    assert_eq!(f64::EPSILON, epsilon.value);
+    assert_eq!(CFloatType::Double, epsilon.c_type);
+    assert_eq!(f32::MAX, probe.float_constant("FLT_MAX").unwrap().value);
+}
@@ -0,0 +1,42 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use probe_c_api::{FunctionCheck, Probe};
+
+fn new_stdio_probe() -> Probe<'static> {
+    Probe::builder().header("<stdio.h>").build().unwrap()
+}
+
+#[test]
+fn declared_function_links() {
+    let probe = new_stdio_probe();
+    assert_eq!(FunctionCheck { declared: true, links: true },
+               probe.has_function("puts").unwrap());
+}
+
+#[test]
+fn undeclared_function_links() {
+    let probe = <Probe>::default();
+    assert_eq!(FunctionCheck { declared: false, links: true },
+               probe.has_function("puts").unwrap());
+}
+
+#[test]
+fn missing_function() {
+    let probe = new_stdio_probe();
+    assert_eq!(FunctionCheck { declared: false, links: false },
+               probe.has_function("probe_c_api_no_such_function").unwrap());
+}
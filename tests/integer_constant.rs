@@ -51,7 +51,6 @@ fn signed_integer_constant_from_macro() {
 
 #[test]
 fn unsigned_integer_constant() {
-    use std::u64;
     let probe = new_constant_probe();
     assert_eq!(u64::MAX,
                probe.unsigned_integer_constant("huge_64_bit_int").unwrap());
@@ -62,3 +61,30 @@ fn unsigned_integer_constant_from_macro() {
     let probe = new_constant_probe();
     assert_eq!(1, probe.unsigned_integer_constant("ONE").unwrap());
 }
+
+#[test]
+fn wide_integer_constants_of_ordinary_width() {
+    let probe = new_constant_probe();
+    assert_eq!(-1, probe.wide_signed_integer_constant("NEGATIVE_ONE").unwrap());
+    assert_eq!(u128::from(u64::MAX),
+               probe.wide_unsigned_integer_constant("huge_64_bit_int")
+                    .unwrap());
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn wide_integer_constants_of_128_bits() {
+    let probe = new_constant_probe();
+    assert_eq!(-(1i128 << 100) - 5,
+               probe.wide_signed_integer_constant("huge_negative_128_bit_int")
+                    .unwrap());
+    assert_eq!(u128::MAX,
+               probe.wide_unsigned_integer_constant("huge_128_bit_int")
+                    .unwrap());
+}
+
+#[test]
+fn wide_unsigned_integer_constant_rejects_negative() {
+    let probe = new_constant_probe();
+    assert!(probe.wide_unsigned_integer_constant("NEGATIVE_ONE").is_err());
+}
@@ -0,0 +1,115 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+extern crate probe_c_api;
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{self, Command};
+use std::thread;
+use std::time::Duration;
+
+use probe_c_api::{Jobserver, Probe, Query};
+
+// Create a FIFO to act as a jobserver, holding `tokens` tokens. The returned
+// file keeps the FIFO open, and can be used to check the tokens afterward.
+fn fifo_jobserver(name: &str, tokens: usize) -> (PathBuf, File) {
+    let path = env::temp_dir().join(format!("probe-c-api-jobserver-{}-{}",
+                                            process::id(), name));
+    let _ = fs::remove_file(&path);
+    assert!(Command::new("mkfifo").arg(&path).status().unwrap().success());
+    let mut fifo = OpenOptions::new().read(true).write(true).open(&path)
+                                     .unwrap();
+    fifo.write_all(&vec![b'+'; tokens]).unwrap();
+    (path, fifo)
+}
+
+fn test_queries() -> Vec<Query> {
+    ["char", "short", "int", "long long", "not_a_type", "float"].iter()
+        .map(|type_| Query::SizeOf(type_.to_string()))
+        .collect()
+}
+
+fn check_results(probe: &Probe, jobserver: &Jobserver) {
+    let results = probe.run_parallel_with_jobserver(&test_queries(), 4,
+                                                    jobserver);
+    let sizes: Vec<Option<u64>> = results.iter().map(|result| {
+        result.as_ref().ok().and_then(|value| value.as_u64())
+    }).collect();
+    assert_eq!(vec![Some(1), Some(2), Some(4), Some(8), None, Some(4)],
+               sizes);
+}
+
+#[test]
+fn no_jobserver_in_flags() {
+    assert!(Jobserver::from_makeflags("").is_none());
+    assert!(Jobserver::from_makeflags("-j4 -k").is_none());
+}
+
+#[test]
+fn unusable_jobservers() {
+    assert!(Jobserver::from_makeflags(
+        "--jobserver-auth=fifo:/probe-c-api/no/such/fifo").is_none());
+    assert!(Jobserver::from_makeflags("--jobserver-auth=998,999").is_none());
+    assert!(Jobserver::from_makeflags("--jobserver-fds=bad").is_none());
+}
+
+#[test]
+fn jobserver_with_tokens() {
+    let (path, mut fifo) = fifo_jobserver("tokens", 2);
+    let flags = format!("-j3 --jobserver-auth=fifo:{}", path.display());
+    let jobserver = Jobserver::from_makeflags(&flags).unwrap();
+    check_results(&Probe::default(), &jobserver);
+    // Both tokens must have been returned.
+    let mut tokens = [0; 2];
+    fifo.read_exact(&mut tokens).unwrap();
+    assert_eq!(b"++", &tokens);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn jobserver_with_only_implicit_token() {
+    let (path, _fifo) = fifo_jobserver("implicit", 0);
+    let flags = format!("--jobserver-auth=fifo:{}", path.display());
+    let jobserver = Jobserver::from_makeflags(&flags).unwrap();
+    check_results(&Probe::default(), &jobserver);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn broken_jobserver_falls_back_to_implicit_token() {
+    // Reading a token from an empty regular file fails.
+    let path = env::temp_dir().join(format!("probe-c-api-jobserver-{}-broken",
+                                            process::id()));
+    File::create(&path).unwrap();
+    let flags = format!("--jobserver-auth=fifo:{}", path.display());
+    let jobserver = Jobserver::from_makeflags(&flags).unwrap();
+    // Compiling slowly keeps the implicit token busy, so the other thread
+    // has to ask the jobserver for a token.
+    let probe = Probe::new(vec![], &env::temp_dir(),
+                           |source_path, exe_path| {
+                               thread::sleep(Duration::from_millis(200));
+                               Command::new("gcc").arg(source_path)
+                                                  .arg("-o").arg(exe_path)
+                                                  .output()
+                           },
+                           |exe_path| Command::new(exe_path).output())
+                      .unwrap();
+    check_results(&probe, &jobserver);
+    fs::remove_file(&path).unwrap();
+}
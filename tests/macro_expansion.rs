@@ -0,0 +1,93 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::process::Command;
+
+use probe_c_api::{MacroExpansion, MacroKind, Probe};
+
+fn new_macro_probe() -> Probe<'static> {
+    Probe::new(
+        vec!["\"tests/test_macros.h\"".into()],
+        &env::temp_dir(),
+        |source_path, exe_path| {
+            Command::new("gcc").arg(source_path)
+                               .arg(format!("-I{}", env!("CARGO_MANIFEST_DIR")))
+                               .arg("-o").arg(exe_path)
+                               .output()
+        },
+        |exe_path| {
+            Command::new(exe_path).output()
+        },
+    ).unwrap()
+}
+
+fn object_like(expansion: &str) -> Option<MacroExpansion> {
+    Some(MacroExpansion {
+        kind: MacroKind::ObjectLike,
+        expansion: expansion.to_string(),
+    })
+}
+
+fn function_like(parameters: &[&str], variadic: bool, expansion: &str)
+                 -> Option<MacroExpansion> {
+    Some(MacroExpansion {
+        kind: MacroKind::FunctionLike {
+            parameters: parameters.iter().map(|p| p.to_string()).collect(),
+            variadic,
+        },
+        expansion: expansion.to_string(),
+    })
+}
+
+#[test]
+fn undefined_macro() {
+    let probe = new_macro_probe();
+    assert_eq!(None, probe.macro_expansion("NOT_A_MACRO").unwrap());
+}
+
+#[test]
+fn object_like_macros() {
+    let probe = new_macro_probe();
+    assert_eq!(object_like("(1 + 2)"),
+               probe.macro_expansion("OBJECT_MACRO").unwrap());
+    assert_eq!(object_like("(1 + 2) * 3"),
+               probe.macro_expansion("NESTED_MACRO").unwrap());
+    assert_eq!(object_like("\"a \\\"quoted\\\" string\""),
+               probe.macro_expansion("STRING_MACRO").unwrap());
+    assert_eq!(object_like(""),
+               probe.macro_expansion("EMPTY_MACRO").unwrap());
+    assert_eq!(object_like("1, 2"),
+               probe.macro_expansion("LIST_MACRO").unwrap());
+    assert_eq!(object_like("SELF_REFERENCE"),
+               probe.macro_expansion("SELF_REFERENCE").unwrap());
+}
+
+#[test]
+fn function_like_macros() {
+    let probe = new_macro_probe();
+    assert_eq!(function_like(&[], false, "42"),
+               probe.macro_expansion("NO_PARAMETERS").unwrap());
+    assert_eq!(function_like(&["_0"], false, "((_0) + 1)"),
+               probe.macro_expansion("ONE_PARAMETER").unwrap());
+    assert_eq!(function_like(&["_0", "_1"], false,
+                             "((_0) > (_1) ? (_0) : (_1))"),
+               probe.macro_expansion("MAXIMUM").unwrap());
+    assert_eq!(function_like(&["_0"], true, "printf(_0, ...)"),
+               probe.macro_expansion("LOG").unwrap());
+    assert_eq!(function_like(&[], true, "printf(...)"),
+               probe.macro_expansion("PRINT").unwrap());
+}
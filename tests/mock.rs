@@ -0,0 +1,105 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(any(unix, windows))]
+
+extern crate probe_c_api;
+
+use probe_c_api::{CProbeError, CompileErrorKind, MockBackend, MockOutcome};
+
+#[test]
+fn mock_scripted_in_order() {
+    let mock = MockBackend::new();
+    mock.then(MockOutcome::runs("4\n"))
+        .then(MockOutcome::runs("8\n"));
+    let probe = mock.probe(vec!["<stdint.h>".into()]).unwrap();
+    assert_eq!(4, probe.size_of("int32_t").unwrap());
+    assert_eq!(8, probe.size_of("int64_t").unwrap());
+    assert_eq!(0, mock.remaining());
+    let sources = mock.sources();
+    assert_eq!(2, sources.len());
+    assert!(sources[0].contains("#include <stdint.h>"));
+    assert!(sources[0].contains("sizeof(int32_t)"));
+    assert!(sources[1].contains("sizeof(int64_t)"));
+}
+
+#[test]
+fn mock_scripted_by_pattern() {
+    let mock = MockBackend::new();
+    mock.when_contains("sizeof(char)", MockOutcome::runs("1\n"))
+        .then(MockOutcome::runs("2\n"));
+    let probe = mock.probe(vec![]).unwrap();
+    assert_eq!(1, probe.size_of("char").unwrap());
+    assert_eq!(2, probe.size_of("short").unwrap());
+    assert_eq!(1, probe.size_of("char").unwrap());
+    assert_eq!(3, mock.sources().len());
+    // Once the script runs out, the backend reports an I/O error.
+    assert!(matches!(probe.size_of("int"), Err(CProbeError::IoError(..))));
+}
+
+#[test]
+fn mock_compile_error() {
+    let mock = MockBackend::new();
+    mock.then(MockOutcome::compile_error(
+        "source-1.c:4:47: error: 'unknown_t' undeclared (first use in this \
+         function)\n"));
+    let probe = mock.probe(vec![]).unwrap();
+    match probe.size_of("unknown_t").unwrap_err() {
+        CProbeError::CompileError(ref failure) => {
+            assert_eq!(CompileErrorKind::UndeclaredIdentifier(
+                           "unknown_t".into()),
+                       failure.kind());
+            let line = mock.sources()[0].lines().nth(3).unwrap().to_string();
+            assert_eq!(Some(line), failure.first_error().unwrap()
+                                          .source_line.clone());
+        }
+        error => panic!("unexpected error: {}", error),
+    }
+    mock.then(MockOutcome::compile_error("internal compiler error\n"));
+    assert!(matches!(probe.size_of("int"),
+                     Err(CProbeError::CompileError(..))));
+}
+
+#[test]
+fn mock_run_and_other_errors() {
+    let mock = MockBackend::new();
+    mock.then(MockOutcome::run_error(3, "segmentation fault\n"))
+        .then(MockOutcome::runs("not a number\n"))
+        .then(MockOutcome::io_error("compiler not found"));
+    let probe = mock.probe(vec![]).unwrap();
+    match probe.size_of("int").unwrap_err() {
+        CProbeError::RunError(_, ref output) => {
+            assert_eq!(Some(3), output.status.code());
+            assert_eq!(b"segmentation fault\n", &output.stderr[..]);
+        }
+        error => panic!("unexpected error: {}", error),
+    }
+    assert!(matches!(probe.size_of("int"),
+                     Err(CProbeError::OtherError(..))));
+    match probe.size_of("int").unwrap_err() {
+        CProbeError::IoError(ref error) => {
+            assert_eq!("compiler not found", error.to_string());
+        }
+        error => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn mock_run_output_missing() {
+    let mock = MockBackend::new();
+    mock.then(MockOutcome::new(MockOutcome::output(0, "", ""), None));
+    let probe = mock.probe(vec![]).unwrap();
+    assert!(matches!(probe.size_of("int"), Err(CProbeError::IoError(..))));
+    assert!(probe.check_compile("int main(void) {}").is_err());
+}
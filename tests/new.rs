@@ -35,10 +35,7 @@ fn new_probe_checks_directory() {
         |_, _| { Command::new(":").output() },
         |_| { Command::new(":").output() },
     );
-    assert!(match new_probe_result {
-        Err(WorkDirNotADirectory(..)) => true,
-        _ => false,
-    });
+    assert!(matches!(new_probe_result, Err(WorkDirNotADirectory(..))));
 }
 
 #[test]
@@ -50,8 +47,5 @@ fn new_probe_errors_on_inaccessible_metadata() {
         |_, _| { Command::new(":").output() },
         |_| { Command::new(":").output() },
     );
-    assert!(match new_probe_result {
-        Err(WorkDirMetadataInaccessible(..)) => true,
-        _ => false,
-    });
+    assert!(matches!(new_probe_result, Err(WorkDirMetadataInaccessible(..))));
 }
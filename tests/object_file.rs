@@ -0,0 +1,87 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::io;
+use std::process::Command;
+
+use probe_c_api::{CProbeError, Endianness, EvaluationStrategy, Probe, Query,
+                  QueryValue};
+
+// A probe that will fail if it ever tries to run a program.
+fn new_object_file_probe() -> Probe<'static> {
+    let mut probe = Probe::new(
+        vec!["\"tests/test_constants.h\"".into()],
+        &env::temp_dir(),
+        |source_path, exe_path| {
+            Command::new("gcc").arg(source_path)
+                               .arg(format!("-I{}", env!("CARGO_MANIFEST_DIR")))
+                               .arg("-o").arg(exe_path)
+                               .output()
+        },
+        |_| {
+            Err(io::Error::new(io::ErrorKind::Other,
+                               "object file probe tried to run a program"))
+        },
+    ).unwrap();
+    probe.set_evaluation_strategy(EvaluationStrategy::ObjectFile);
+    probe
+}
+
+#[test]
+fn object_file_single_queries() {
+    let probe = new_object_file_probe();
+    assert_eq!(1, probe.size_of("char").unwrap());
+    assert!(!probe.is_signed("unsigned int").unwrap());
+    assert!(probe.is_defined_macro("ONE").unwrap());
+    assert_eq!(-1, probe.signed_integer_constant("NEGATIVE_ONE").unwrap());
+    assert_eq!(u64::MAX,
+               probe.unsigned_integer_constant("UINT64_MAX").unwrap());
+}
+
+#[test]
+fn object_file_batch_isolates_bad_query() {
+    let probe = new_object_file_probe();
+    let results = probe.evaluate_batch(&[
+        Query::SizeOf("int32_t".into()),
+        Query::SizeOf("struct not_a_real_struct".into()),
+        Query::SignedIntegerConstant("INT64_MIN".into()),
+        Query::IsSigned("int".into()),
+    ]).unwrap();
+    assert_eq!(QueryValue::Unsigned(4), *results[0].as_ref().unwrap());
+    assert!(matches!(results[1], Err(CProbeError::CompileError(..))));
+    assert_eq!(QueryValue::Signed(i64::MIN), *results[2].as_ref().unwrap());
+    assert_eq!(QueryValue::Bool(true), *results[3].as_ref().unwrap());
+}
+
+#[test]
+fn object_representation_of_integer() {
+    let probe = new_object_file_probe();
+    let target = probe.target_info().unwrap();
+    let bytes = probe.object_representation("uint32_t", "0x01020304").unwrap();
+    let expected = match target.endianness {
+        Endianness::Little => vec![4, 3, 2, 1],
+        Endianness::Big => vec![1, 2, 3, 4],
+    };
+    assert_eq!(expected, bytes);
+}
+
+#[test]
+fn target_word_size_matches_pointers() {
+    let probe = new_object_file_probe();
+    assert_eq!(probe.size_of("void *").unwrap(),
+               probe.target_info().unwrap().word_size);
+}
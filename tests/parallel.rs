@@ -0,0 +1,108 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use probe_c_api::{EvaluationStrategy, Probe, Query, QueryValue};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn probe_is_send_and_sync() {
+    assert_send_sync::<Probe<'static>>();
+}
+
+#[test]
+fn probe_shared_between_threads() {
+    let probe = Arc::new(Probe::default());
+    let workers: Vec<_> = (0..4).map(|_| {
+        let probe = probe.clone();
+        thread::spawn(move || probe.size_of("int").unwrap())
+    }).collect();
+    for worker in workers {
+        assert_eq!(4, worker.join().unwrap());
+    }
+}
+
+fn test_queries() -> Vec<Query> {
+    vec![Query::SizeOf("char".to_string()),
+         Query::SizeOf("short".to_string()),
+         Query::SizeOf("int".to_string()),
+         Query::SizeOf("not_a_type".to_string()),
+         Query::SizeOf("long long".to_string()),
+         Query::IsSigned("unsigned".to_string())]
+}
+
+fn check_results(results: Vec<probe_c_api::CProbeResult<QueryValue>>) {
+    assert_eq!(6, results.len());
+    assert_eq!(Some(1), results[0].as_ref().unwrap().as_u64());
+    assert_eq!(Some(2), results[1].as_ref().unwrap().as_u64());
+    assert_eq!(Some(4), results[2].as_ref().unwrap().as_u64());
+    assert!(results[3].is_err());
+    assert_eq!(Some(8), results[4].as_ref().unwrap().as_u64());
+    assert_eq!(Some(false), results[5].as_ref().unwrap().as_bool());
+}
+
+#[test]
+fn run_parallel_in_order() {
+    let probe = Probe::default();
+    check_results(probe.run_parallel(&test_queries(), 4));
+}
+
+#[test]
+fn run_parallel_with_one_thread() {
+    let probe = Probe::default();
+    check_results(probe.run_parallel(&test_queries(), 0));
+    assert!(probe.run_parallel(&[], 4).is_empty());
+}
+
+#[test]
+fn run_parallel_compile_only() {
+    let mut probe = Probe::default();
+    probe.set_evaluation_strategy(EvaluationStrategy::CompileOnly);
+    check_results(probe.run_parallel(&test_queries(), 3));
+}
+
+#[test]
+fn run_parallel_with_closures() {
+    let compiles = Arc::new(AtomicUsize::new(0));
+    let counter = compiles.clone();
+    let probe = Probe::new(vec![], &env::temp_dir(),
+                           move |source_path, exe_path| {
+                               counter.fetch_add(1, Ordering::SeqCst);
+                               Command::new("gcc").arg(source_path)
+                                                  .arg("-o").arg(exe_path)
+                                                  .output()
+                           },
+                           |exe_path| Command::new(exe_path).output())
+                      .unwrap();
+    check_results(probe.run_parallel(&test_queries(), 2));
+    assert!(compiles.load(Ordering::SeqCst) >= 6);
+}
+
+#[test]
+fn run_parallel_from_builder() {
+    let probe = Probe::builder().evaluation_strategy(
+                                    EvaluationStrategy::CompileOnly)
+                                .build()
+                                .unwrap();
+    assert_eq!(EvaluationStrategy::CompileOnly, probe.evaluation_strategy());
+    check_results(probe.run_parallel(&test_queries(), 2));
+}
@@ -0,0 +1,95 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::path::{Path, PathBuf};
+
+use probe_c_api::{PkgConfig, PkgConfigError, Probe};
+
+fn test_package() -> PkgConfig {
+    PkgConfig::new("probe_test")
+              .search_dir(&Path::new(env!("CARGO_MANIFEST_DIR"))
+                                   .join("tests/pkgconfig"))
+}
+
+#[test]
+fn pkg_config_settings() {
+    let library = test_package().find().unwrap();
+    assert_eq!("1.2.3", library.version);
+    assert_eq!(vec![("PROBE_TEST_FROM_PKG_CONFIG".to_string(),
+                     Some("1".to_string()))],
+               library.options.defines);
+    assert_eq!(vec!["-lm".to_string()], library.options.link_flags);
+    assert!(library.options.include_dirs.iter().any(|dir| {
+        dir.join("test_types.h").is_file()
+    }));
+}
+
+#[test]
+fn pkg_config_probe() {
+    let library = test_package().find().unwrap();
+    let probe = Probe::builder().pkg_config(&library)
+                                .header("\"test_types.h\"")
+                                .header("<math.h>")
+                                .build()
+                                .unwrap();
+    assert_eq!(4, probe.size_of("alias_unsigned_32_bit").unwrap());
+    assert_eq!(1, probe.signed_integer_constant("PROBE_TEST_FROM_PKG_CONFIG")
+                       .unwrap());
+    assert!(probe.has_function("cos").unwrap().links);
+}
+
+#[test]
+fn pkg_config_version_range() {
+    assert!(test_package().min_version("1.0").max_version("2.0").find()
+                          .is_ok());
+    assert!(matches!(test_package().min_version("1.3").find(),
+                     Err(PkgConfigError::PackageError(..))));
+    assert!(matches!(test_package().max_version("1.2.3").find(),
+                     Err(PkgConfigError::PackageError(..))));
+}
+
+#[test]
+fn pkg_config_missing_package() {
+    assert!(matches!(PkgConfig::new("probe_c_api_no_such_package").find(),
+                     Err(PkgConfigError::PackageError(..))));
+    assert!(matches!(Probe::from_pkg_config("probe_c_api_no_such_package",
+                                            &[]),
+                     Err(PkgConfigError::PackageError(..))));
+}
+
+#[test]
+fn pkg_config_link_flags_in_order() {
+    // Directories are searched in order, so the first, which does not exist,
+    // is skipped.
+    let library = PkgConfig::new("probe_test_link")
+                            .search_dir(&PathBuf::from("/probe-c-api/none"))
+                            .search_dir(&Path::new(env!("CARGO_MANIFEST_DIR"))
+                                             .join("tests/pkgconfig-link"))
+                            .find()
+                            .unwrap();
+    assert_eq!("0.1.0", library.version);
+    assert_eq!(vec!["-Wl,--push-state,--as-needed", "-lm", "-Wl,--pop-state"],
+               library.options.link_flags);
+    assert!(library.options.libraries.is_empty());
+    assert!(library.options.flags.is_empty());
+    let probe = Probe::builder().pkg_config(&library)
+                                .header("\"test_types.h\"")
+                                .header("<math.h>")
+                                .build()
+                                .unwrap();
+    assert!(probe.is_defined_macro("PROBE_TEST_LINK").unwrap());
+    assert!(probe.has_function("cos").unwrap().links);
+}
@@ -0,0 +1,146 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use probe_c_api::{MacroExpansion, MacroKind, PreprocessMode, Probe};
+
+fn new_preprocess_probe() -> Probe<'static> {
+    let mut probe = Probe::new(
+        vec!["\"tests/test_macros.h\"".into()],
+        &env::temp_dir(),
+        |source_path, exe_path| {
+            Command::new("gcc").arg(source_path)
+                               .arg(format!("-I{}", env!("CARGO_MANIFEST_DIR")))
+                               .arg("-o").arg(exe_path)
+                               .output()
+        },
+        |_| {
+            panic!("a probe program was run");
+        },
+    ).unwrap();
+    probe.set_preprocess(|source_path, mode| {
+        let mut command = Command::new("gcc");
+        if mode == PreprocessMode::DefinedMacros {
+            command.arg("-dM");
+        }
+        command.arg("-E")
+               .arg(format!("-I{}", env!("CARGO_MANIFEST_DIR")))
+               .arg(source_path)
+               .output()
+    });
+    probe
+}
+
+#[test]
+fn preprocess_source() {
+    let probe = new_preprocess_probe();
+    let preprocessed = probe.preprocess("#define TWO 2\n\
+                                         int x = TWO;\n").unwrap();
+    assert!(preprocessed.contains("int x = 2;"));
+}
+
+#[test]
+fn preprocess_requires_command() {
+    let probe = Probe::new(vec![], &env::temp_dir(),
+                           |_, _| { panic!("unexpected compilation"); },
+                           |_| { panic!("unexpected run"); }).unwrap();
+    assert!(!probe.has_preprocess());
+    assert!(probe.preprocess("int x;\n").is_err());
+}
+
+#[test]
+fn defined_macros() {
+    let probe = new_preprocess_probe();
+    let macros = probe.defined_macros().unwrap();
+    assert!(macros.iter().any(|name| name == "OBJECT_MACRO"));
+    assert!(macros.iter().any(|name| name == "MAXIMUM"));
+    assert!(probe.is_defined_macro("LOG").unwrap());
+    assert!(!probe.is_defined_macro("NOT_A_MACRO").unwrap());
+}
+
+#[test]
+fn included_files() {
+    let probe = new_preprocess_probe();
+    let files = probe.included_files().unwrap();
+    assert!(files.iter().any(|file| file.ends_with("tests/test_macros.h")));
+    assert!(files.iter().any(|file| file.file_name()
+                                        == Path::new("stdio.h").file_name()));
+}
+
+#[test]
+fn macro_expansion_with_preprocessor() {
+    let probe = new_preprocess_probe();
+    assert_eq!(None, probe.macro_expansion("NOT_A_MACRO").unwrap());
+    assert_eq!(Some(MacroExpansion {
+                   kind: MacroKind::ObjectLike,
+                   expansion: "(1 + 2) * 3".to_string(),
+               }),
+               probe.macro_expansion("NESTED_MACRO").unwrap());
+    assert_eq!(Some(MacroExpansion {
+                   kind: MacroKind::ObjectLike,
+                   expansion: "\"a \\\"quoted\\\" string\"".to_string(),
+               }),
+               probe.macro_expansion("STRING_MACRO").unwrap());
+    assert_eq!(Some(MacroExpansion {
+                   kind: MacroKind::FunctionLike {
+                       parameters: vec!["a".to_string(), "b".to_string()],
+                       variadic: false,
+                   },
+                   expansion: "((a) > (b) ? (a) : (b))".to_string(),
+               }),
+               probe.macro_expansion("MAXIMUM").unwrap());
+    assert_eq!(Some(MacroExpansion {
+                   kind: MacroKind::FunctionLike {
+                       parameters: vec!["format".to_string()],
+                       variadic: true,
+                   },
+                   expansion: "printf(format, ...)".to_string(),
+               }),
+               probe.macro_expansion("LOG").unwrap());
+    assert_eq!(Some(MacroExpansion {
+                   kind: MacroKind::FunctionLike {
+                       parameters: vec![],
+                       variadic: false,
+                   },
+                   expansion: "42".to_string(),
+               }),
+               probe.macro_expansion("NO_PARAMETERS").unwrap());
+}
+
+#[test]
+fn is_defined_macro_agrees_without_preprocess_command() {
+    let with_preprocess = Probe::builder().build().unwrap();
+    assert!(with_preprocess.has_preprocess());
+    let without_preprocess = Probe::new(
+        vec![],
+        &env::temp_dir(),
+        |source_path, exe_path| {
+            Command::new("gcc").arg(source_path)
+                               .arg("-o").arg(exe_path)
+                               .output()
+        },
+        |exe_path| {
+            Command::new(exe_path).output()
+        },
+    ).unwrap();
+    for probe in &[with_preprocess, without_preprocess] {
+        assert!(probe.is_defined_macro("EOF").unwrap());
+        assert!(!probe.is_defined_macro("PROBE_C_API_NOT_DEFINED").unwrap());
+    }
+}
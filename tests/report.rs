@@ -0,0 +1,154 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use probe_c_api::{ApiSpec, BindingItem, CProbeError, EvaluationStrategy,
+                  ItemValue, Probe, ProbeReport, Query, QueryValue,
+                  ReportEntry, ReportError};
+
+fn test_report() -> ProbeReport {
+    let spec = ApiSpec::from_file(&Path::new(env!("CARGO_MANIFEST_DIR"))
+                                             .join("tests/test_api.toml"))
+                       .unwrap();
+    spec.report().unwrap()
+}
+
+#[test]
+fn report_contents() {
+    let report = test_report();
+    assert_eq!(EvaluationStrategy::CompileAndRun, report.toolchain.strategy);
+    assert_eq!("\"test_types.h\"", report.toolchain.headers[0]);
+    assert!(report.toolchain.compiler.contains("gcc"));
+    assert_eq!(6, report.entries.len());
+    assert_eq!(BindingItem::Constant("PROBE_TEST_VALUE".to_string()),
+               report.entries[1].item);
+    assert_eq!(ItemValue::Constant {
+                   rust_type: "i32".to_string(),
+                   value: "42".to_string(),
+               },
+               *report.entries[1].result.as_ref().unwrap());
+    assert_eq!(ItemValue::Bytes(b"1.2.3".to_vec()),
+               *report.entries[2].result.as_ref().unwrap());
+}
+
+#[test]
+fn report_json_round_trip() {
+    let report = test_report();
+    let json = report.to_json();
+    let reloaded = ProbeReport::from_json(&json).unwrap();
+    assert_eq!(report.toolchain, reloaded.toolchain);
+    assert_eq!(json, reloaded.to_json());
+    assert_eq!(report.bindings().source, reloaded.bindings().source);
+    // Each entry starts on its own line, so that reports diff well.
+    assert!(json.contains("\n    {\n      \"kind\": \"constant\",\n      \
+                           \"name\": \"PROBE_TEST_VALUE\",\n"),
+            "{}", json);
+}
+
+#[test]
+fn report_errors_round_trip() {
+    let probe = probe_c_api::Probe::builder().build().unwrap();
+    let mut writer = probe_c_api::BindingsWriter::new(&probe);
+    writer.constant("NOT_A_CONSTANT")
+          .function("not_a_function_either");
+    let report = writer.report();
+    assert!(matches!(report.entries[0].result,
+                     Err(CProbeError::CompileError(..))));
+    let reloaded = ProbeReport::from_json(&report.to_json()).unwrap();
+    assert!(matches!(reloaded.entries[0].result,
+                     Err(CProbeError::OtherError(..))));
+    let (bindings, reloaded_bindings) = (report.bindings(),
+                                         reloaded.bindings());
+    assert_eq!(bindings.source, reloaded_bindings.source);
+    assert_eq!(bindings.diagnostics[0].to_string(),
+               reloaded_bindings.diagnostics[0].to_string());
+    assert!(bindings.source.contains("pub const HAVE_not_a_function_either: \
+                                      bool = false;\n"));
+}
+
+#[test]
+fn report_file() {
+    let report = test_report();
+    let path = env::temp_dir().join(format!("probe-c-api-report-{}.json",
+                                            process::id()));
+    report.write_to_file(&path).unwrap();
+    let reloaded = ProbeReport::read_from_file(&path).unwrap();
+    assert_eq!(report.entries.len(), reloaded.entries.len());
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(ProbeReport::read_from_file(&path),
+                     Err(ReportError::IoError(..))));
+}
+
+#[test]
+fn report_parse_errors() {
+    let parse_error = |text: &str| {
+        match ProbeReport::from_json(text) {
+            Err(ReportError::ParseError(message)) => message,
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+    };
+    assert!(parse_error("{").contains("line 1"));
+    assert!(parse_error("{\"format\": \"something else\"}")
+                .contains("not a probe-c-api report"));
+    let json = test_report().to_json();
+    let message = parse_error(&json.replace("\"rust_type\"", "\"rust\""));
+    assert!(message.contains("entry 0: missing `rust_type`"), "{}", message);
+    let message = parse_error(&json.replace("\"compile-and-run\"",
+                                            "\"guess\""));
+    assert!(message.contains("unknown strategy `guess`"), "{}", message);
+}
+
+#[test]
+fn report_queries_round_trip() {
+    let probe = Probe::builder().build().unwrap();
+    let mut report = ProbeReport::new(&probe);
+    let queries = vec![Query::SizeOf("int".to_string()),
+                       Query::IsSigned("unsigned".to_string()),
+                       Query::SignedIntegerConstant("-5".to_string()),
+                       Query::SizeOf("not_a_type".to_string())];
+    let results = report.evaluate_batch(&probe, &queries);
+    assert_eq!(Some(4), results[0].as_ref().unwrap().as_u64());
+    assert!(results[3].is_err());
+    assert_eq!(4, report.queries.len());
+    assert_eq!(queries[2], report.queries[2].query);
+    let json = report.to_json();
+    let reloaded = ProbeReport::from_json(&json).unwrap();
+    assert_eq!(json, reloaded.to_json());
+    assert_eq!(queries[0], reloaded.queries[0].query);
+    assert_eq!(QueryValue::Bool(false),
+               *reloaded.queries[1].result.as_ref().unwrap());
+    assert_eq!(QueryValue::Signed(-5),
+               *reloaded.queries[2].result.as_ref().unwrap());
+    assert!(matches!(reloaded.queries[3].result,
+                     Err(CProbeError::OtherError(..))));
+}
+
+#[test]
+fn report_mismatched_value() {
+    let mut report = test_report();
+    report.entries.push(ReportEntry {
+        item: BindingItem::Typedef("mismatched_t".to_string()),
+        result: Ok(ItemValue::Bytes(b"text".to_vec())),
+    });
+    let bindings = report.bindings();
+    let diagnostic = bindings.diagnostics.last().unwrap();
+    assert_eq!("mismatched_t", diagnostic.c_name);
+    assert!(matches!(diagnostic.error, CProbeError::OtherError(..)));
+}
@@ -31,10 +31,7 @@ fn sizeof_char() {
 fn sizeof_compilation_error() {
     let probe = <Probe>::default();
     let error = probe.size_of("><").unwrap_err();
-    assert!(match error {
-        CProbeError::CompileError(..) => true,
-        _ => false,
-    });
+    assert!(matches!(error, CProbeError::CompileError(..)));
 }
 
 #[test]
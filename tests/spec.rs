@@ -0,0 +1,138 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use probe_c_api::{ApiSpec, BindingItem, SpecError};
+
+fn test_spec() -> ApiSpec {
+    ApiSpec::from_file(&Path::new(env!("CARGO_MANIFEST_DIR"))
+                                 .join("tests/test_api.toml"))
+            .unwrap()
+}
+
+fn parse_error_line(text: &str) -> usize {
+    match ApiSpec::parse(text) {
+        Err(SpecError::ParseError { line, .. }) => line,
+        result => panic!("expected a parse error, got {:?}", result),
+    }
+}
+
+#[test]
+fn spec_items() {
+    let spec = test_spec();
+    assert_eq!(5, spec.headers().len());
+    assert_eq!(&BindingItem::Typedef("alias_unsigned_16_bit".to_string()),
+               &spec.items()[0]);
+    assert_eq!(&BindingItem::Enum {
+                   enum_type: "enum status".to_string(),
+                   rust_name: "status".to_string(),
+                   enumerators: vec!["STATUS_OK".to_string()],
+               },
+               &spec.items()[4]);
+    assert_eq!(6, spec.items().len());
+}
+
+#[test]
+fn spec_probe() {
+    let probe = test_spec().probe().unwrap();
+    assert_eq!(42, probe.signed_integer_constant("PROBE_TEST_VALUE")
+                        .unwrap());
+    assert!(probe.is_defined_macro("PROBE_TEST_DEFINED").unwrap());
+}
+
+#[test]
+fn spec_generate() {
+    let bindings = test_spec().generate().unwrap();
+    assert!(bindings.diagnostics.is_empty(), "{:?}", bindings.diagnostics);
+    for expected in &["pub type alias_unsigned_16_bit = u16;\n",
+                      "pub const PROBE_TEST_VALUE: i32 = 42;\n",
+                      "pub const VERSION_STRING: &[u8] = b\"1.2.3\";\n",
+                      "pub const HAVE_cos: bool = true;\n",
+                      "pub const STATUS_OK: status = 0;\n",
+                      "pub struct Inner {\n"] {
+        assert!(bindings.source.contains(expected), "{}", bindings.source);
+    }
+}
+
+#[test]
+fn spec_syntax() {
+    let spec = ApiSpec::parse("# Comment.\n\
+                               headers = [ # Another comment.\n\
+                               \"<stdio.h>\",\n\
+                               '<c:\\\\path\\\\x.h>', \"\\\"q\\u0041.h\\\"\",\n\
+                               ]\n\
+                               [compiler]\n\
+                               clang = false # After a value.\n\
+                               \"std\" = 'c99'\n\
+                               flags = ['', \"\"]\n\
+                               [[item]]\n\
+                               kind='function'\n\
+                               name=\"puts\"\n")
+                     .unwrap();
+    assert_eq!(&["<stdio.h>".to_string(), "<c:\\\\path\\\\x.h>".to_string(),
+                 "\"qA.h\"".to_string()],
+               spec.headers());
+    assert_eq!(&[BindingItem::Function("puts".to_string())], spec.items());
+}
+
+#[test]
+fn spec_errors() {
+    assert_eq!(2, parse_error_line("headers = []\nheaders = []\n"));
+    assert_eq!(1, parse_error_line("headers = [\"a\" \"b\"]"));
+    assert_eq!(2, parse_error_line("\nheaders = \"unterminated\n"));
+    assert_eq!(3, parse_error_line("[compiler]\nstd = 'c11'\ncompiler = 'x'"));
+    assert_eq!(2, parse_error_line("[compiler]\nstrategy = 'guess'\n"));
+    assert_eq!(1, parse_error_line("[tables]\n"));
+    assert_eq!(1, parse_error_line("[[item]]\nkind = 'typedef'\n\n"));
+    assert_eq!(1, parse_error_line("[[item]]\nkind = 'global'\nname = 'x'\n"));
+    assert_eq!(3, parse_error_line("[[item]]\nkind = 'struct'\n\
+                                    fields = 'x'\nname = 'x'\n"));
+    assert_eq!(1, parse_error_line("headers = [1]\n"));
+}
+
+#[test]
+fn spec_unsupported_toml() {
+    for text in &["[compiler]\nstd.version = 'c11'\n",
+                  "[compiler.extra]\n",
+                  "headers = \"\"\"<stdio.h>\"\"\"\n",
+                  "headers = '''<stdio.h>'''\n",
+                  "headers = { first = '<stdio.h>' }\n",
+                  "[compiler]\nstd = 11\n"] {
+        match ApiSpec::parse(text) {
+            Err(SpecError::ParseError { ref message, .. }) => {
+                assert!(message.contains("not supported"), "{}", message);
+                assert!(message.contains("`ApiSpec`"), "{}", message);
+            }
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+    }
+}
+
+#[test]
+fn spec_program_on_path() {
+    // A program without a path separator is not relative to the spec file.
+    let path = env::temp_dir().join(format!("probe-c-api-spec-{}.toml",
+                                            process::id()));
+    fs::write(&path, "[compiler]\nprogram = 'gcc'\n").unwrap();
+    let spec = ApiSpec::from_file(&path);
+    fs::remove_file(&path).unwrap();
+    let probe = spec.unwrap().probe().unwrap();
+    assert_eq!(4, probe.size_of("int").unwrap());
+}
@@ -0,0 +1,117 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate probe_c_api;
+
+use std::env;
+use std::process::Command;
+
+use probe_c_api::{CProbeError, Probe};
+
+fn new_struct_probe() -> Probe<'static> {
+    Probe::new(
+        vec!["\"tests/test_structs.h\"".into()],
+        &env::temp_dir(),
+        |source_path, exe_path| {
+            Command::new("gcc").arg(source_path)
+                               .arg(format!("-I{}", env!("CARGO_MANIFEST_DIR")))
+                               .arg("-o").arg(exe_path)
+                               .output()
+        },
+        |exe_path| {
+            Command::new(exe_path).output()
+        },
+    ).unwrap()
+}
+
+#[test]
+fn field_offsets_and_sizes() {
+    let probe = new_struct_probe();
+    assert_eq!(0, probe.offset_of("struct outer", "tag").unwrap());
+    assert_eq!(8, probe.offset_of("struct outer", "inner").unwrap());
+    assert_eq!(16, probe.offset_of("struct outer", "inner.y").unwrap());
+    assert_eq!(8, probe.field_size_of("struct outer", "inner.y").unwrap());
+    assert_eq!(2, probe.field_align_of("struct outer", "inner.x").unwrap());
+}
+
+#[test]
+fn struct_layout_with_trailing_padding() {
+    let probe = new_struct_probe();
+    let layout = probe.struct_layout("padded_tail", &["a", "b"]).unwrap();
+    assert_eq!(8, layout.size);
+    assert_eq!(4, layout.alignment);
+    assert_eq!(2, layout.fields.len());
+    assert_eq!("b", layout.fields[1].name);
+    assert_eq!(4, layout.fields[1].offset);
+    assert_eq!(1, layout.fields[1].size);
+    assert_eq!(3, layout.trailing_padding);
+}
+
+#[test]
+fn struct_layout_missing_field() {
+    let probe = new_struct_probe();
+    let error = probe.struct_layout("struct outer", &["tag", "nonexistent"])
+                     .unwrap_err();
+    assert!(matches!(error, CProbeError::CompileError(..)));
+}
+
+#[test]
+fn rust_struct_with_padding_and_alignment() {
+    let probe = new_struct_probe();
+    let definition = probe.rust_struct_definition("struct outer", "Outer",
+                                                  &["tag", "inner.x", "count"])
+                          .unwrap();
+    assert_eq!("#[repr(C, align(8))]\n\
+                pub struct Outer {\n    \
+                    pub tag: u8,\n    \
+                    pub _padding0: [u8; 7],\n    \
+                    pub inner_x: i16,\n    \
+                    pub _padding1: [u8; 14],\n    \
+                    pub count: i32,\n    \
+                    pub _padding2: [u8; 4],\n\
+                }\n",
+               definition);
+}
+
+#[test]
+fn rust_struct_with_opaque_field() {
+    let probe = new_struct_probe();
+    let definition = probe.rust_struct_definition("struct outer", "Outer",
+                                                  &["tag", "inner", "count"])
+                          .unwrap();
+    assert_eq!("#[repr(C, align(8))]\n\
+                pub struct Outer {\n    \
+                    pub tag: u8,\n    \
+                    pub _padding0: [u8; 7],\n    \
+                    pub inner: [u8; 16],\n    \
+                    pub count: i32,\n    \
+                    pub _padding1: [u8; 4],\n\
+                }\n",
+               definition);
+}
+
+#[test]
+fn rust_struct_keeps_similar_names_distinct() {
+    let probe = new_struct_probe();
+    let definition = probe.rust_struct_definition("struct similar_names",
+                                                  "SimilarNames",
+                                                  &["type", "type_"])
+                          .unwrap();
+    assert_eq!("#[repr(C)]\n\
+                pub struct SimilarNames {\n    \
+                    pub r#type: i32,\n    \
+                    pub type_: f32,\n\
+                }\n",
+               definition);
+}
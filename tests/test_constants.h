@@ -21,3 +21,21 @@ const int negative_one = -1;
 const uint64_t huge_64_bit_int = UINT64_MAX;
 
 #define ONE 1
+
+#define FLOAT_THIRD (1.0f / 3.0f)
+
+const double double_tenth = 0.1;
+
+#define LONG_DOUBLE_HALF 0.5L
+
+#define VERSION_STRING "1.2.3"
+
+const char tricky_string[] = " line one\nsay \"hi\"\t\xff\xfe\n";
+
+#define EMBEDDED_NUL "a\0b"
+
+#ifdef __SIZEOF_INT128__
+const __int128 huge_negative_128_bit_int = -((__int128) 1 << 100) - 5;
+
+const unsigned __int128 huge_128_bit_int = ~(unsigned __int128) 0;
+#endif
@@ -0,0 +1,34 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+enum color {
+    RED,
+    GREEN,
+    BLUE,
+};
+
+enum status {
+    STATUS_ERROR = -1,
+    STATUS_OK = 0,
+    STATUS_SUCCESS = 0,
+    STATUS_PENDING = 5,
+};
+
+enum large_unsigned {
+    LARGE_UNSIGNED = 0xffffffffu,
+};
+
+enum too_large {
+    TOO_LARGE = 0xffffffffffffffffull,
+};
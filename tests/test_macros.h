@@ -0,0 +1,29 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+#include <stdio.h>
+
+#define OBJECT_MACRO (1 + 2)
+#define NESTED_MACRO OBJECT_MACRO * 3
+#define STRING_MACRO "a \"quoted\" string"
+#define EMPTY_MACRO
+#define LIST_MACRO 1, 2
+#define SELF_REFERENCE SELF_REFERENCE
+
+#define NO_PARAMETERS() 42
+#define ONE_PARAMETER(x) ((x) + 1)
+#define MAXIMUM(a, b) ((a) > (b) ? (a) : (b))
+#define LOG(format, ...) printf(format, __VA_ARGS__)
+#define PRINT(...) printf(__VA_ARGS__)
@@ -0,0 +1,36 @@
+// Copyright 2015 Sean Patrick Santos
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#include <inttypes.h>
+
+struct inner {
+    int16_t x;
+    int64_t y;
+};
+
+struct outer {
+    uint8_t tag;
+    struct inner inner;
+    int32_t count;
+};
+
+typedef struct {
+    int32_t a;
+    uint8_t b;
+} padded_tail;
+
+struct similar_names {
+    int32_t type;
+    float type_;
+};
@@ -24,5 +24,10 @@ typedef uint16_t alias_unsigned_16_bit;
 typedef uint32_t alias_unsigned_32_bit;
 typedef uint64_t alias_unsigned_64_bit;
 
+#ifdef __SIZEOF_INT128__
+typedef __int128 alias_signed_128_bit;
+typedef unsigned __int128 alias_unsigned_128_bit;
+#endif
+
 typedef float alias_float_32_bit;
 typedef double alias_float_64_bit;